@@ -0,0 +1,262 @@
+use crate::data_structures::li_chao_tree::{better, eval, widen, Line};
+use crate::data_structures::monoid::{Idempotent, Max, Min};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+// Compares the fractions a / b and c / d for b, d > 0 without multiplying them out,
+// through the continued fraction expansion, so large numerators cannot overflow.
+fn cmp_fraction(a: i128, b: i128, c: i128, d: i128) -> Ordering {
+    let (qa, qc) = (a.div_euclid(b), c.div_euclid(d));
+    if qa != qc {
+        return qa.cmp(&qc);
+    }
+
+    let (ra, rc) = (a.rem_euclid(b), c.rem_euclid(d));
+    match (ra == 0, rc == 0) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // ra / b < rc / d iff b / ra > d / rc
+        (false, false) => cmp_fraction(d, rc, b, ra),
+    }
+}
+
+/// Monotone Convex Hull Trick
+/// Lower (or upper, depending on `M`) envelope of lines y = kx + b kept in a deque, for lines
+/// added in sorted order of slopes: non-increasing slopes for `Min`, non-decreasing for `Max`,
+/// so every new line is the best one for large x.
+/// Adding a line is amortized O(1), a query O(log n), or amortized O(1) through
+/// `query_monotone` when the query points are non-decreasing.
+/// Intersections are compared exactly in i128, so slopes, intercepts and points may use
+/// the full range of any integer type that converts into `i64`.
+#[derive(Debug, Clone)]
+pub struct MonotoneCht<T, M> {
+    lines: VecDeque<Line>,
+    _marker: std::marker::PhantomData<(T, M)>,
+}
+
+pub type MinMonotoneCht<T> = MonotoneCht<T, Min<i128>>;
+pub type MaxMonotoneCht<T> = MonotoneCht<T, Max<i128>>;
+
+impl<T, M> MonotoneCht<T, M>
+where
+    T: Copy + Into<i64>,
+    M: Idempotent<S = i128>,
+{
+    pub fn new() -> Self {
+        MonotoneCht {
+            lines: VecDeque::new(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Number of lines on the envelope.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // whether `b` never wins once `a` and `c` are on the envelope, slopes are ordered a, b, c
+    fn is_redundant(a: &Line, b: &Line, c: &Line) -> bool {
+        // b wins only between x(a, b) and x(b, c), where x(a, b) = (b.1 - a.1) / (a.0 - b.0)
+        let (num_ab, den_ab) = (b.1 - a.1, a.0 - b.0);
+        let (num_bc, den_bc) = (c.1 - b.1, b.0 - c.0);
+        // normalize to positive denominators, both have the same sign as slopes are monotone
+        let (num_ab, den_ab, num_bc, den_bc) = if den_ab < 0 {
+            (-num_ab, -den_ab, -num_bc, -den_bc)
+        } else {
+            (num_ab, den_ab, num_bc, den_bc)
+        };
+
+        cmp_fraction(num_ab, den_ab, num_bc, den_bc) != Ordering::Less
+    }
+
+    pub fn add_line(&mut self, k: T, b: T) {
+        let line = (widen(k), widen(b));
+
+        if let Some(&last) = self.lines.back() {
+            assert!(
+                last.0 == line.0 || better::<M>(line.0, last.0),
+                "slopes have to be added in monotone order"
+            );
+
+            if last.0 == line.0 {
+                if !better::<M>(line.1, last.1) {
+                    return;
+                }
+                self.lines.pop_back();
+            }
+        }
+
+        while self.lines.len() >= 2 {
+            let n = self.lines.len();
+            if !Self::is_redundant(&self.lines[n - 2], &self.lines[n - 1], &line) {
+                break;
+            }
+            self.lines.pop_back();
+        }
+
+        self.lines.push_back(line);
+    }
+
+    /// Best value of all lines at `x`, `None` if there are no lines.
+    pub fn query(&self, x: T) -> Option<i128> {
+        if self.lines.is_empty() {
+            return None;
+        }
+
+        // the best line moves to the back of the deque as x grows
+        let x = widen(x);
+        let (mut lo, mut hi) = (0, self.lines.len() - 1);
+        while lo < hi {
+            let m = (hi - lo) / 2 + lo;
+            if better::<M>(eval(&self.lines[m + 1], x), eval(&self.lines[m], x)) {
+                lo = m + 1;
+            } else {
+                hi = m;
+            }
+        }
+
+        Some(eval(&self.lines[lo], x))
+    }
+
+    /// Same as `query`, but drops the lines in front that cannot win anymore, so the
+    /// query points have to be non-decreasing across calls.
+    pub fn query_monotone(&mut self, x: T) -> Option<i128> {
+        let x = widen(x);
+        while self.lines.len() >= 2
+            && !better::<M>(eval(&self.lines[0], x), eval(&self.lines[1], x))
+        {
+            self.lines.pop_front();
+        }
+
+        self.lines.front().map(|line| eval(line, x))
+    }
+}
+
+impl<T, M> Default for MonotoneCht<T, M>
+where
+    T: Copy + Into<i64>,
+    M: Idempotent<S = i128>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cmp_fraction, MaxMonotoneCht, MinMonotoneCht};
+    use rand::prelude::*;
+    use std::cmp::{Ordering, Reverse};
+
+    #[test]
+    fn test_cmp_fraction() {
+        assert_eq!(cmp_fraction(1, 3, 2, 6), Ordering::Equal);
+        assert_eq!(cmp_fraction(-1, 2, -1, 3), Ordering::Less);
+        assert_eq!(cmp_fraction(7, 5, 4, 3), Ordering::Greater);
+
+        // the cross products of these overflow i128
+        let big = i64::MAX as i128 * 2;
+        assert_eq!(cmp_fraction(big, big - 1, big - 1, big - 2), Ordering::Less);
+        assert_eq!(cmp_fraction(big, big - 1, big, big - 1), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_monotone_cht() {
+        let mut cht = MinMonotoneCht::new();
+        assert_eq!(cht.query(0), None);
+
+        cht.add_line(2, 0);
+        cht.add_line(1, 1);
+        cht.add_line(0, 5);
+        cht.add_line(0, 3);
+        cht.add_line(-1, 100);
+        assert_eq!(cht.query(-10), Some(-20));
+        assert_eq!(cht.query(1), Some(2));
+        assert_eq!(cht.query(4), Some(3));
+        assert_eq!(cht.query(1000), Some(-900));
+
+        let mut cht = MaxMonotoneCht::new();
+        cht.add_line(-1i32, 0);
+        cht.add_line(0, -5);
+        cht.add_line(1, 0);
+        // y = 0x - 5 is below the other two everywhere
+        assert_eq!(cht.len(), 2);
+        assert_eq!(cht.query(-3), Some(3));
+        assert_eq!(cht.query(7), Some(7));
+    }
+
+    #[test]
+    fn test_monotone_cht_extreme_values() {
+        let big = u32::MAX as i128;
+        let mut cht = MaxMonotoneCht::new();
+        cht.add_line(0, u32::MAX);
+        cht.add_line(u32::MAX, 0);
+        assert_eq!(cht.query(u32::MAX), Some(big * big));
+        assert_eq!(cht.query(0), Some(big));
+
+        let (big, small) = (i64::MAX as i128, i64::MIN as i128);
+        let mut cht = MinMonotoneCht::new();
+        cht.add_line(i64::MAX, i64::MAX);
+        cht.add_line(i64::MIN, i64::MAX);
+        assert_eq!(cht.query(i64::MIN), Some(small * big + big));
+        assert_eq!(cht.query(i64::MAX), Some(small * big + big));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_monotone_cht_unsorted_slopes() {
+        let mut cht = MinMonotoneCht::new();
+        cht.add_line(1, 0);
+        cht.add_line(2, 0);
+    }
+
+    #[test]
+    fn test_monotone_cht_random() {
+        let mut rng = thread_rng();
+        for _ in 0..50 {
+            let mut lines: Vec<(i64, i64)> = (0..100)
+                .map(|_| {
+                    (
+                        if rng.gen_bool(0.5) {
+                            rng.gen::<i64>()
+                        } else {
+                            rng.gen_range(-100..100)
+                        },
+                        rng.gen::<i64>(),
+                    )
+                })
+                .collect();
+            lines.sort_by_key(|&(k, _)| Reverse(k));
+
+            let mut min_cht = MinMonotoneCht::new();
+            let mut monotone = MinMonotoneCht::new();
+            let mut max_cht = MaxMonotoneCht::new();
+            for &(k, b) in &lines {
+                min_cht.add_line(k, b);
+                monotone.add_line(k, b);
+            }
+            for &(k, b) in lines.iter().rev() {
+                max_cht.add_line(k, b);
+            }
+
+            let mut xs: Vec<i64> = (0..100)
+                .map(|_| rng.gen_range(-1_000_000_000..1_000_000_000))
+                .collect();
+            xs.sort();
+            for x in xs {
+                let values = lines
+                    .iter()
+                    .map(|&(k, b)| k as i128 * x as i128 + b as i128);
+                let expected = values.clone().min();
+                assert_eq!(min_cht.query(x), expected);
+                assert_eq!(monotone.query_monotone(x), expected);
+                assert_eq!(max_cht.query(x), values.max());
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::data_structures::monoid::Monoid;
+
+/// Disjoint Sparse Table
+/// O(1) range queries for any associative operation after O(n log n) preprocessing.
+/// On level h the array is cut at every odd multiple of 2^h (the "mid" points) and for each
+/// mid the table keeps the suffix aggregates of the 2^h values left of it and the prefix
+/// aggregates of the 2^h values right of it. For l < r the highest differing bit of l and r
+/// selects the single level on which [l, r] crosses a mid, so the answer is the combination
+/// of one suffix and one prefix and no element is counted twice.
+#[derive(Debug, Clone)]
+pub struct DisjointSparseTable<M: Monoid> {
+    nums: Vec<M::S>,
+    st: Vec<Vec<M::S>>,
+}
+
+impl<M: Monoid> DisjointSparseTable<M> {
+    pub fn new(nums: Vec<M::S>) -> Self {
+        let n = nums.len();
+        let mut st = Vec::new();
+
+        let mut half = 1;
+        while half < n {
+            let mut level = vec![M::identity(); n];
+
+            for mid in (half..n).step_by(2 * half) {
+                level[mid - 1] = nums[mid - 1].clone();
+                for i in (mid - half..mid - 1).rev() {
+                    level[i] = M::combine(&nums[i], &level[i + 1]);
+                }
+
+                level[mid] = nums[mid].clone();
+                for i in mid + 1..n.min(mid + half) {
+                    level[i] = M::combine(&level[i - 1], &nums[i]);
+                }
+            }
+
+            st.push(level);
+            half <<= 1;
+        }
+
+        DisjointSparseTable { nums, st }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nums.is_empty()
+    }
+
+    /// Aggregate of the inclusive range [l, r] in O(1).
+    pub fn query(&self, l: usize, r: usize) -> M::S {
+        if l > r {
+            return M::identity();
+        }
+
+        if l == r {
+            return self.nums[l].clone();
+        }
+
+        let h = (usize::BITS - 1 - (l ^ r).leading_zeros()) as usize;
+        M::combine(&self.st[h][l], &self.st[h][r])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisjointSparseTable;
+    use crate::data_structures::monoid::{MatrixProduct, Monoid, ProductMod, Sum};
+    use rand::prelude::*;
+
+    fn check<M>(nums: Vec<M::S>)
+    where
+        M: Monoid,
+        M::S: PartialEq + std::fmt::Debug,
+    {
+        let st = DisjointSparseTable::<M>::new(nums.clone());
+        for l in 0..nums.len() {
+            let mut expected = M::identity();
+            for (r, num) in nums.iter().enumerate().skip(l) {
+                expected = M::combine(&expected, num);
+                assert_eq!(st.query(l, r), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_disjoint_sparse_table_sum() {
+        let mut rng = thread_rng();
+        for n in 0..40 {
+            check::<Sum<i64>>((0..n).map(|_| rng.gen_range(-1000..1000)).collect());
+        }
+
+        let st = DisjointSparseTable::<Sum<i32>>::new(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(st.query(0, 7), 36);
+        assert_eq!(st.query(3, 4), 9);
+        assert_eq!(st.query(4, 3), 0);
+    }
+
+    #[test]
+    fn test_disjoint_sparse_table_product_mod() {
+        let mut rng = thread_rng();
+        check::<ProductMod<1_000_000_007>>(
+            (0..100).map(|_| rng.gen_range(0..1_000_000_007)).collect(),
+        );
+    }
+
+    #[test]
+    fn test_disjoint_sparse_table_matrix_product() {
+        let mut rng = thread_rng();
+        let nums = (0..50)
+            .map(|_| {
+                [
+                    [rng.gen_range(-3..=3), rng.gen_range(-3..=3)],
+                    [rng.gen_range(-3..=3), rng.gen_range(-3..=3)],
+                ]
+            })
+            .collect::<Vec<[[i128; 2]; 2]>>();
+        check::<MatrixProduct<i128, 2>>(nums[..20].to_vec());
+
+        let st = DisjointSparseTable::<MatrixProduct<i128, 2>>::new(nums.clone());
+        assert_ne!(
+            st.query(0, 1),
+            MatrixProduct::<i128, 2>::combine(&nums[1], &nums[0])
+        );
+    }
+
+    #[test]
+    fn test_disjoint_sparse_table_string_hash() {
+        const MOD: u64 = 1_000_000_007;
+        const BASE: u64 = 131;
+
+        // (hash, BASE^len) of a substring, concatenation is associative but not commutative
+        struct Hash;
+
+        impl Monoid for Hash {
+            type S = (u64, u64);
+
+            fn identity() -> (u64, u64) {
+                (0, 1)
+            }
+
+            fn combine(a: &(u64, u64), b: &(u64, u64)) -> (u64, u64) {
+                ((a.0 * b.1 + b.0) % MOD, a.1 * b.1 % MOD)
+            }
+        }
+
+        let s = "abracadabra";
+        let st = DisjointSparseTable::<Hash>::new(s.bytes().map(|c| (c as u64, BASE)).collect());
+
+        let hash = |t: &str| t.bytes().fold(0, |acc, c| (acc * BASE + c as u64) % MOD);
+        for l in 0..s.len() {
+            for r in l..s.len() {
+                assert_eq!(st.query(l, r).0, hash(&s[l..=r]));
+            }
+        }
+
+        // "abra" occurs at 0 and 7
+        assert_eq!(st.query(0, 3), st.query(7, 10));
+        assert_ne!(st.query(0, 3), st.query(1, 4));
+    }
+}
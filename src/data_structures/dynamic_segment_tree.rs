@@ -0,0 +1,310 @@
+use crate::data_structures::lazy_segment_tree::Action;
+use crate::data_structures::monoid::{pow, Monoid};
+use std::cmp::{max, min};
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone)]
+struct Node<S, F> {
+    left: usize,
+    right: usize,
+    val: S,
+    // `None` if there is no pending tag, so huge children are not touched by identity tags
+    lazy: Option<F>,
+}
+
+/// Dynamic Segment Tree
+/// Lazy segment tree over the coordinates [lo, hi] of an integer type `C` (`i64` by default,
+/// `u64` works as well) that allocates nodes only when they are touched by an update or
+/// query, so huge ranges like [0, 10^18) need no coordinate compression. Every position
+/// starts with the value `default`.
+/// O(log (hi - lo)) nodes are created per operation.
+/// The range may have up to 2^64 - 1 coordinates, but the aggregate of every segment has to
+/// fit into `M::S` and the lengths of tagged segments into what `A` accepts: over the full
+/// `i64` range a `Sum<i64>` needs a zero default, and tags on segments of more than
+/// `i64::MAX` positions panic.
+#[derive(Debug, Clone)]
+pub struct DynamicSegmentTree<M: Monoid, A: Action<M>, C = i64> {
+    lo: i128,
+    hi: i128,
+    default: M::S,
+    nodes: Vec<Node<M::S, A::F>>,
+    _coordinate: PhantomData<C>,
+}
+
+#[inline]
+fn mid(left: i128, right: i128) -> i128 {
+    (right - left) / 2 + left
+}
+
+#[inline]
+fn len(left: i128, right: i128) -> u64 {
+    (right - left + 1) as u64
+}
+
+impl<M, A, C> DynamicSegmentTree<M, A, C>
+where
+    M: Monoid,
+    A: Action<M>,
+    C: Copy + Into<i128>,
+{
+    pub fn new(lo: C, hi: C, default: M::S) -> Self {
+        let (lo, hi) = (lo.into(), hi.into());
+        assert!(lo <= hi, "empty coordinate range");
+        assert!(
+            hi - lo < u64::MAX as i128,
+            "the number of coordinates has to fit into u64"
+        );
+
+        let mut segment_tree = DynamicSegmentTree {
+            lo,
+            hi,
+            default,
+            nodes: Vec::new(),
+            _coordinate: PhantomData,
+        };
+        // the root is node 0, so 0 marks a missing child
+        segment_tree.new_node(lo, hi);
+
+        segment_tree
+    }
+
+    /// Number of allocated nodes.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Aggregate of the values in the inclusive range [i, j].
+    pub fn query(&mut self, i: C, j: C) -> M::S {
+        let (i, j) = (max(i.into(), self.lo), min(j.into(), self.hi));
+        if i > j {
+            return M::identity();
+        }
+
+        self.query_internal(0, self.lo, self.hi, i, j)
+    }
+
+    /// Applies the tag `f` to every value in the inclusive range [i, j].
+    pub fn update(&mut self, i: C, j: C, f: A::F) {
+        let (i, j) = (max(i.into(), self.lo), min(j.into(), self.hi));
+        if i > j {
+            return;
+        }
+
+        self.update_internal(0, self.lo, self.hi, i, j, &f);
+    }
+
+    pub fn get(&mut self, i: C) -> M::S {
+        self.query(i, i)
+    }
+
+    pub fn set(&mut self, i: C, val: M::S) {
+        let i = i.into();
+        assert!(self.lo <= i && i <= self.hi, "index out of range");
+        self.set_internal(0, self.lo, self.hi, i, val);
+    }
+
+    fn new_node(&mut self, left: i128, right: i128) -> usize {
+        self.nodes.push(Node {
+            left: 0,
+            right: 0,
+            val: pow::<M>(&self.default, len(left, right)),
+            lazy: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn apply_node(&mut self, p: usize, len: u64, f: &A::F) {
+        let node = &mut self.nodes[p];
+        node.val = A::apply(f, &node.val, len as usize);
+        node.lazy = Some(match &node.lazy {
+            Some(g) => A::compose(f, g),
+            None => f.clone(),
+        });
+    }
+
+    // Creates the missing children and pushes the pending tag down to them.
+    fn propagate(&mut self, p: usize, left: i128, right: i128) {
+        let m = mid(left, right);
+        if self.nodes[p].left == 0 {
+            self.nodes[p].left = self.new_node(left, m);
+        }
+        if self.nodes[p].right == 0 {
+            self.nodes[p].right = self.new_node(m + 1, right);
+        }
+
+        if let Some(f) = self.nodes[p].lazy.take() {
+            let (l, r) = (self.nodes[p].left, self.nodes[p].right);
+            self.apply_node(l, len(left, m), &f);
+            self.apply_node(r, len(m + 1, right), &f);
+        }
+    }
+
+    fn pull(&mut self, p: usize) {
+        let (l, r) = (self.nodes[p].left, self.nodes[p].right);
+        self.nodes[p].val = M::combine(&self.nodes[l].val, &self.nodes[r].val);
+    }
+
+    fn update_internal(&mut self, p: usize, left: i128, right: i128, i: i128, j: i128, f: &A::F) {
+        if i > j {
+            return;
+        }
+
+        if left >= i && right <= j {
+            self.apply_node(p, len(left, right), f);
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = mid(left, right);
+        let (l, r) = (self.nodes[p].left, self.nodes[p].right);
+        self.update_internal(l, left, m, i, min(m, j), f);
+        self.update_internal(r, m + 1, right, max(i, m + 1), j, f);
+        self.pull(p);
+    }
+
+    fn set_internal(&mut self, p: usize, left: i128, right: i128, i: i128, val: M::S) {
+        if left == right {
+            self.nodes[p].val = val;
+            self.nodes[p].lazy = None;
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = mid(left, right);
+        if i <= m {
+            self.set_internal(self.nodes[p].left, left, m, i, val);
+        } else {
+            self.set_internal(self.nodes[p].right, m + 1, right, i, val);
+        }
+        self.pull(p);
+    }
+
+    fn query_internal(&mut self, p: usize, left: i128, right: i128, i: i128, j: i128) -> M::S {
+        if i > j {
+            return M::identity();
+        }
+
+        if left >= i && right <= j {
+            return self.nodes[p].val.clone();
+        }
+
+        self.propagate(p, left, right);
+        let m = mid(left, right);
+        let (l, r) = (self.nodes[p].left, self.nodes[p].right);
+        let res_left = self.query_internal(l, left, m, i, min(m, j));
+        let res_right = self.query_internal(r, m + 1, right, max(i, m + 1), j);
+        M::combine(&res_left, &res_right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynamicSegmentTree;
+    use crate::data_structures::lazy_segment_tree::{RangeAdd, RangeAssign};
+    use crate::data_structures::monoid::{Max, Sum};
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    #[test]
+    fn test_dynamic_segment_tree_huge_range() {
+        let mut st =
+            DynamicSegmentTree::<Sum<i64>, RangeAdd<i64>>::new(0, 1_000_000_000_000_000_000 - 1, 0);
+
+        st.update(0, 999_999_999_999_999_999, 1);
+        st.set(500_000_000_000_000_000, 10);
+        st.update(1_000, 1_999, 5);
+
+        assert_eq!(
+            st.query(0, 999_999_999_999_999_999),
+            1_000_000_000_000_000_000 + 9 + 5_000
+        );
+        assert_eq!(st.query(1_500, 2_500), 1_001 + 500 * 5);
+        assert_eq!(st.get(500_000_000_000_000_000), 10);
+        assert_eq!(st.get(123_456_789_012), 1);
+        assert!(st.num_nodes() < 1_000);
+    }
+
+    #[test]
+    fn test_dynamic_segment_tree_negative_coordinates() {
+        let mut st =
+            DynamicSegmentTree::<Max<i64>, RangeAssign<i64>>::new(i64::MIN, i64::MAX - 1, 0);
+
+        st.update(-1_000_000_000_000, -5, Some(7));
+        st.set(i64::MAX - 1, 9);
+
+        assert_eq!(st.query(i64::MIN, -1_000_000_000_001), 0);
+        assert_eq!(st.query(-10, 10), 7);
+        assert_eq!(st.query(-4, i64::MAX - 2), 0);
+        assert_eq!(st.query(i64::MIN, i64::MAX), 9);
+    }
+
+    #[test]
+    fn test_dynamic_segment_tree_full_range_sum() {
+        // the largest supported range, pushing down must not apply tags to huge children
+        let mut st = DynamicSegmentTree::<Sum<i64>, RangeAdd<i64>>::new(i64::MIN, i64::MAX - 1, 0);
+
+        st.set(0, 5);
+        st.update(-3, 3, 2);
+        st.set(i64::MIN, -1);
+
+        assert_eq!(st.get(0), 7);
+        assert_eq!(st.get(3), 2);
+        assert_eq!(st.query(i64::MIN, i64::MAX - 1), 5 + 7 * 2 - 1);
+        assert_eq!(st.query(4, i64::MAX), 0);
+    }
+
+    #[test]
+    fn test_dynamic_segment_tree_u64_coordinates() {
+        let mut st = DynamicSegmentTree::<Max<u64>, RangeAssign<u64>, u64>::new(0, u64::MAX - 1, 0);
+
+        st.update(u64::MAX / 2, u64::MAX - 1, Some(3));
+        st.set(u64::MAX - 1, 8);
+        st.set(5, 1);
+
+        assert_eq!(st.query(0, u64::MAX / 2 - 1), 1);
+        assert_eq!(st.query(u64::MAX / 2, u64::MAX - 2), 3);
+        assert_eq!(st.query(0, u64::MAX), 8);
+        assert_eq!(st.get(6), 0);
+
+        let mut st = DynamicSegmentTree::<Sum<u64>, RangeAdd<u64>, u64>::new(1, u64::MAX, 0);
+        st.update(u64::MAX - 9, u64::MAX, 2);
+        assert_eq!(st.query(0, u64::MAX), 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "segment length does not fit into the value type")]
+    fn test_dynamic_segment_tree_tag_too_long() {
+        // the sum of a tag over more than i64::MAX positions does not fit into i64
+        let mut st = DynamicSegmentTree::<Sum<i64>, RangeAdd<i64>>::new(i64::MIN, i64::MAX - 1, 0);
+        st.update(i64::MIN, i64::MAX - 1, 0);
+    }
+
+    #[test]
+    fn test_dynamic_segment_tree_random() {
+        let (lo, hi) = (-50, 149);
+        let mut rng = thread_rng();
+        let mut nums = vec![3i64; (hi - lo + 1) as usize];
+        let mut st = DynamicSegmentTree::<Sum<i64>, RangeAdd<i64>>::new(lo, hi, 3);
+
+        for _ in 0..5000 {
+            let v1 = rng.gen_range(lo..=hi);
+            let v2 = rng.gen_range(lo..=hi);
+            let (l, r) = (min(v1, v2), max(v1, v2));
+            let range = (l - lo) as usize..=(r - lo) as usize;
+
+            match rng.gen_range(0..3) {
+                0 => {
+                    let f = rng.gen_range(-100..100);
+                    nums[range].iter_mut().for_each(|x| *x += f);
+                    st.update(l, r, f);
+                }
+                1 => {
+                    let val = rng.gen_range(-100..100);
+                    nums[(l - lo) as usize] = val;
+                    st.set(l, val);
+                }
+                _ => assert_eq!(st.query(l, r), nums[range].iter().sum::<i64>()),
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
+use crate::data_structures::monoid::{Commutative, Invertible, Max, Min};
 use num::{Bounded, Zero};
-use std::cmp::min;
 use std::ops::{AddAssign, Sub};
 
 #[derive(Debug, Clone, Default)]
@@ -48,111 +48,362 @@ where
     }
 }
 
-impl From<Vec<i32>> for FenwickTree<i32> {
-    fn from(nums: Vec<i32>) -> Self {
-        let mut fenwick_tree = FenwickTree::new(nums.len(), 0);
+impl<T> FenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero + PartialOrd,
+{
+    /// Smallest index whose prefix sum is `>= target`, or `n` if there is none.
+    /// Binary lifting over the tree in O(log n), all values have to be non-negative.
+    pub fn lower_bound(&self, target: T) -> usize {
+        let mut pos = 0;
+        let mut target = target;
+        let mut step = if self.n == 0 {
+            0
+        } else {
+            1 << (usize::BITS - 1 - self.n.leading_zeros())
+        };
+
+        // tree[i] covers the prefix (pos, pos + step] when i + 1 = pos + step
+        while step > 0 {
+            if pos + step <= self.n && self.tree[pos + step - 1] < target {
+                pos += step;
+                target = target - self.tree[pos - 1];
+            }
+            step >>= 1;
+        }
 
-        for (i, val) in nums.iter().enumerate() {
-            fenwick_tree.tree[i] += val;
-            let r = i | (i + 1);
-            fenwick_tree.tree[r] += fenwick_tree.tree[i];
+        pos
+    }
+}
+
+impl<T> FenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    /// Appends a value at index `n`, O(log n).
+    pub fn push(&mut self, val: T) {
+        let idx = self.n;
+        let lo = idx & (idx + 1);
+
+        // the new node covers [lo, idx], all but the last element are already in the tree
+        let mut node = val;
+        if lo < idx {
+            node += self.range_sum(lo as i64, idx as i64 - 1);
         }
 
-        fenwick_tree
+        self.tree.push(node);
+        self.n += 1;
     }
 }
 
-impl From<Vec<i64>> for FenwickTree<i64> {
-    fn from(nums: Vec<i64>) -> Self {
-        let mut fenwick_tree = FenwickTree::new(nums.len(), 0);
+/// O(n) construction, every node pushes its accumulated value to its parent.
+impl<T> From<Vec<T>> for FenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    fn from(nums: Vec<T>) -> Self {
+        let n = nums.len();
+        let mut tree = nums;
 
-        for (i, val) in nums.iter().enumerate() {
-            fenwick_tree.tree[i] += val;
+        for i in 0..n {
             let r = i | (i + 1);
-            fenwick_tree.tree[r] += fenwick_tree.tree[i];
+            if r < n {
+                let val = tree[i];
+                tree[r] += val;
+            }
         }
 
-        fenwick_tree
+        FenwickTree { n, tree }
     }
 }
 
-impl From<Vec<i16>> for FenwickTree<i16> {
-    fn from(nums: Vec<i16>) -> Self {
-        let mut fenwick_tree = FenwickTree::new(nums.len(), 0);
+impl<T> FromIterator<T> for FenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
 
-        for (i, val) in nums.iter().enumerate() {
-            fenwick_tree.tree[i] += val;
-            let r = i | (i + 1);
-            fenwick_tree.tree[r] += fenwick_tree.tree[i];
+impl<T> Extend<T> for FenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.push(val);
         }
+    }
+}
 
-        fenwick_tree
+// x * k using only additions, O(log k)
+fn mul_usize<T>(x: T, k: usize) -> T
+where
+    T: Copy + AddAssign + Zero,
+{
+    let mut res = T::zero();
+    let mut base = x;
+    let mut k = k;
+
+    while k > 0 {
+        if k & 1 == 1 {
+            res += base;
+        }
+
+        k >>= 1;
+        if k > 0 {
+            let doubled = base;
+            base += doubled;
+        }
     }
+
+    res
 }
 
-impl From<Vec<i8>> for FenwickTree<i8> {
-    fn from(nums: Vec<i8>) -> Self {
-        let mut fenwick_tree = FenwickTree::new(nums.len(), 0);
+/// Fenwick tree with range add and point query.
+/// Stores the difference array, so the value at `idx` is the prefix sum up to `idx`.
+/// The positive and negative parts of the differences are kept in separate trees and only
+/// subtracted when querying, so unsigned types work as long as the values stay non-negative.
+#[derive(Debug, Clone, Default)]
+pub struct RangeUpdateFenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    pos: FenwickTree<T>,
+    neg: FenwickTree<T>,
+}
 
-        for (i, val) in nums.iter().enumerate() {
-            fenwick_tree.tree[i] += val;
-            let r = i | (i + 1);
-            fenwick_tree.tree[r] += fenwick_tree.tree[i];
+impl<T> RangeUpdateFenwickTree<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    pub fn new(n: usize) -> Self {
+        RangeUpdateFenwickTree {
+            pos: FenwickTree::new(n, T::zero()),
+            neg: FenwickTree::new(n, T::zero()),
         }
+    }
+
+    /// Adds `delta` to every element in [l, r].
+    pub fn range_add(&mut self, l: usize, r: usize, delta: T) {
+        self.pos.add(l, delta);
+        self.neg.add(r + 1, delta);
+    }
 
-        fenwick_tree
+    pub fn get(&self, idx: usize) -> T {
+        self.pos.sum(idx as i64) - self.neg.sum(idx as i64)
     }
 }
 
+/// Fenwick tree with range add and range sum.
+/// Adding `delta` on [l, r] changes the prefix sum up to `i` by `delta * (i + 1) - delta * l`
+/// for `i` in [l, r] and by `delta * (r + 1) - delta * l` for `i > r`, so two trees keep the
+/// coefficients of `(i + 1)` and two the constant terms.
+/// Like in `RangeUpdateFenwickTree` the positive and negative parts are kept apart, so
+/// unsigned types work as long as the values stay non-negative.
 #[derive(Debug, Clone, Default)]
-pub struct MinFenwickTree<T>
+pub struct RangeFenwickTree<T>
 where
-    T: Copy + AddAssign + Sub<Output = T> + Zero + Bounded + Ord,
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
 {
-    n: usize,
-    tree: Vec<T>,
+    mul_pos: FenwickTree<T>,
+    mul_neg: FenwickTree<T>,
+    add_pos: FenwickTree<T>,
+    add_neg: FenwickTree<T>,
 }
 
-impl<T> MinFenwickTree<T>
+impl<T> RangeFenwickTree<T>
 where
-    T: Copy + AddAssign + Sub<Output = T> + Zero + Bounded + Ord,
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
 {
     pub fn new(n: usize) -> Self {
-        MinFenwickTree {
-            n,
-            tree: vec![T::max_value(); n],
+        RangeFenwickTree {
+            mul_pos: FenwickTree::new(n, T::zero()),
+            mul_neg: FenwickTree::new(n, T::zero()),
+            add_pos: FenwickTree::new(n, T::zero()),
+            add_neg: FenwickTree::new(n, T::zero()),
         }
     }
 
-    pub fn get_min(&self, r: i32) -> T {
-        let mut r = r;
-        let mut ret = T::max_value();
+    /// Adds `delta` to every element in [l, r].
+    pub fn range_add(&mut self, l: usize, r: usize, delta: T) {
+        self.mul_pos.add(l, delta);
+        self.mul_neg.add(r + 1, delta);
+        self.add_neg.add(l, mul_usize(delta, l));
+        self.add_pos.add(r + 1, mul_usize(delta, r + 1));
+    }
 
-        while r >= 0 {
-            ret = min(ret, self.tree[r as usize]);
-            r = (r & (r + 1)) - 1
+    pub fn sum(&self, r: i64) -> T {
+        if r < 0 {
+            return T::zero();
         }
 
-        ret
+        // subtract last, the coefficient and every partial result stay non-negative
+        let coefficient = self.mul_pos.sum(r) - self.mul_neg.sum(r);
+        let mut res = mul_usize(coefficient, r as usize + 1);
+        res += self.add_pos.sum(r);
+        res - self.add_neg.sum(r)
+    }
+
+    pub fn range_sum(&self, l: i64, r: i64) -> T {
+        self.sum(r) - self.sum(l - 1)
+    }
+}
+
+/// Ordered multiset over a fixed universe of keys.
+/// The keys are compressed to indices of a Fenwick tree counting the occurrences,
+/// so all operations take O(log n).
+#[derive(Debug, Clone, Default)]
+pub struct OrderedMultiset<K: Ord> {
+    keys: Vec<K>,
+    counts: FenwickTree<i64>,
+    len: usize,
+}
+
+impl<K: Ord> OrderedMultiset<K> {
+    pub fn new(universe: Vec<K>) -> Self {
+        let mut keys = universe;
+        keys.sort();
+        keys.dedup();
+
+        OrderedMultiset {
+            counts: FenwickTree::new(keys.len(), 0),
+            keys,
+            len: 0,
+        }
+    }
+
+    fn index(&self, key: &K) -> Option<usize> {
+        self.keys.binary_search(key).ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    pub fn update(&mut self, idx: usize, val: T) {
+    /// Panics if the key is not part of the universe.
+    pub fn insert(&mut self, key: &K) {
+        let idx = self.index(key).expect("key is not part of the universe");
+        self.counts.add(idx, 1);
+        self.len += 1;
+    }
+
+    /// Removes one occurrence of the key, returns false if there was none.
+    pub fn remove(&mut self, key: &K) -> bool {
+        if self.count(key) == 0 {
+            return false;
+        }
+
+        let idx = self.index(key).unwrap();
+        self.counts.add(idx, -1);
+        self.len -= 1;
+        true
+    }
+
+    pub fn count(&self, key: &K) -> usize {
+        match self.index(key) {
+            Some(idx) => self.counts.range_sum(idx as i64, idx as i64) as usize,
+            None => 0,
+        }
+    }
+
+    /// Number of elements strictly less than `key`.
+    pub fn count_less(&self, key: &K) -> usize {
+        let idx = self.keys.partition_point(|k| k < key);
+        self.counts.sum(idx as i64 - 1) as usize
+    }
+
+    /// 1-based rank of `key`, i.e. the number of elements less than `key` plus one.
+    pub fn rank(&self, key: &K) -> usize {
+        self.count_less(key) + 1
+    }
+
+    /// k-th (0-based) smallest element.
+    pub fn kth(&self, k: usize) -> Option<&K> {
+        if k >= self.len {
+            return None;
+        }
+
+        Some(&self.keys[self.counts.lower_bound(k as i64 + 1)])
+    }
+}
+
+/// Fenwick tree over a commutative monoid (min, max, gcd, or, xor, product mod p, ...).
+/// `update` combines a value into a position, so for non-invertible operations values can
+/// only move "in the direction" of the operation (e.g. decrease for min).
+/// Prefix queries work for every monoid, range queries need an inverse.
+#[derive(Debug, Clone)]
+pub struct MonoidFenwickTree<M: Commutative> {
+    n: usize,
+    tree: Vec<M::S>,
+}
+
+pub type MinFenwickTree<T> = MonoidFenwickTree<Min<T>>;
+pub type MaxFenwickTree<T> = MonoidFenwickTree<Max<T>>;
+
+impl<M: Commutative> MonoidFenwickTree<M> {
+    pub fn new(n: usize) -> Self {
+        MonoidFenwickTree {
+            n,
+            tree: vec![M::identity(); n],
+        }
+    }
+
+    /// a[idx] = a[idx] * val
+    pub fn update(&mut self, idx: usize, val: M::S) {
         let mut idx = idx;
 
         while idx < self.n {
-            self.tree[idx] = min(self.tree[idx], val);
+            self.tree[idx] = M::combine(&self.tree[idx], &val);
             idx = idx | (idx + 1);
         }
     }
+
+    /// Aggregate of the prefix [0, r].
+    pub fn prefix(&self, r: i64) -> M::S {
+        let mut r = r;
+        let mut ret = M::identity();
+
+        while r >= 0 {
+            ret = M::combine(&ret, &self.tree[r as usize]);
+            r = (r & (r + 1)) - 1;
+        }
+
+        ret
+    }
 }
 
+impl<T> MonoidFenwickTree<Min<T>>
+where
+    T: Copy + Ord + Bounded,
+{
+    /// Minimum of the prefix [0, r].
+    pub fn get_min(&self, r: i32) -> T {
+        self.prefix(r as i64)
+    }
+}
+
+impl<M: Commutative + Invertible> MonoidFenwickTree<M> {
+    /// Aggregate of the range [l, r].
+    pub fn range(&self, l: i64, r: i64) -> M::S {
+        M::combine(&M::inverse(&self.prefix(l - 1)), &self.prefix(r))
+    }
+}
+
+/// 2D Fenwick tree with point add and rectangle sum.
+/// All methods take the coordinates in (row, col) order.
 #[derive(Debug, Clone, Default)]
 pub struct FenwickTree2D<T>
 where
     T: Copy + AddAssign + Sub<Output = T> + Zero,
 {
-    n: usize,
-    m: usize,
+    rows: usize,
+    cols: usize,
     tree: Vec<Vec<T>>,
 }
 
@@ -160,40 +411,46 @@ impl<T> FenwickTree2D<T>
 where
     T: Copy + AddAssign + Sub<Output = T> + Zero,
 {
-    pub fn new(n: usize, m: usize) -> Self {
+    /// Empty `rows` x `cols` grid. Replaces `new(n, m)`, which took the number of columns
+    /// first, so callers of the old order fail to compile instead of getting transposed data.
+    pub fn with_shape(rows: usize, cols: usize) -> Self {
         FenwickTree2D {
-            n,
-            m,
-            tree: vec![vec![T::zero(); n]; m],
+            rows,
+            cols,
+            tree: vec![vec![T::zero(); cols]; rows],
         }
     }
 
-    pub fn sum(&self, y: i32, x: i32) -> T {
-        let mut y = y;
+    /// Sum of the rectangle [0, row] x [0, col].
+    pub fn sum(&self, row: i32, col: i32) -> T {
+        let mut i = row;
         let mut res = T::zero();
 
-        while y >= 0 {
-            let mut x = x;
-            while x >= 0 {
-                res += self.tree[y as usize][x as usize];
-                x = (x & (x + 1)) - 1;
+        while i >= 0 {
+            let mut j = col;
+            while j >= 0 {
+                res += self.tree[i as usize][j as usize];
+                j = (j & (j + 1)) - 1;
             }
-            y = (y & (y + 1)) - 1;
+            i = (i & (i + 1)) - 1;
         }
 
         res
     }
 
-    pub fn range_sum(&self, y1: i32, x1: i32, y2: i32, x2: i32) -> T {
-        self.sum(y2, x2) - self.sum(y1 - 1, x2) - self.sum(y2, x1 - 1) + self.sum(y1 - 1, x1 - 1)
+    /// Sum of the rectangle [row1, row2] x [col1, col2].
+    pub fn range_sum(&self, row1: i32, col1: i32, row2: i32, col2: i32) -> T {
+        self.sum(row2, col2) - self.sum(row1 - 1, col2) - self.sum(row2, col1 - 1)
+            + self.sum(row1 - 1, col1 - 1)
     }
 
-    pub fn add(&mut self, x: usize, y: usize, val: T) {
-        let mut i = y;
+    /// Adds `val` to the cell (row, col). Replaces `add(x, y, val)`, which took the column first.
+    pub fn point_add(&mut self, row: usize, col: usize, val: T) {
+        let mut i = row;
 
-        while i < self.m {
-            let mut j = x;
-            while j < self.n {
+        while i < self.rows {
+            let mut j = col;
+            while j < self.cols {
                 self.tree[i][j] += val;
                 j |= j + 1;
             }
@@ -203,11 +460,178 @@ where
     }
 }
 
+/// 2D Fenwick tree with rectangle add and rectangle sum.
+/// Keeps the 2D difference array `d`. The sum of [0, x] x [0, y] is
+/// sum of d[i][j] * (x + 1 - i) * (y + 1 - j), which expands into four trees holding
+/// `d`, `d * i`, `d * j` and `d * i * j`.
+/// All methods take the coordinates in (row, col) order.
+#[derive(Debug, Clone, Default)]
+pub struct RangeFenwickTree2D<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    d: FenwickTree2D<T>,
+    di: FenwickTree2D<T>,
+    dj: FenwickTree2D<T>,
+    dij: FenwickTree2D<T>,
+}
+
+impl<T> RangeFenwickTree2D<T>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    pub fn with_shape(rows: usize, cols: usize) -> Self {
+        RangeFenwickTree2D {
+            d: FenwickTree2D::with_shape(rows, cols),
+            di: FenwickTree2D::with_shape(rows, cols),
+            dj: FenwickTree2D::with_shape(rows, cols),
+            dij: FenwickTree2D::with_shape(rows, cols),
+        }
+    }
+
+    fn add_corner(&mut self, row: usize, col: usize, val: T) {
+        self.d.point_add(row, col, val);
+        self.di.point_add(row, col, mul_usize(val, row));
+        self.dj.point_add(row, col, mul_usize(val, col));
+        self.dij
+            .point_add(row, col, mul_usize(mul_usize(val, row), col));
+    }
+
+    /// Adds `val` to every cell of the rectangle [row1, row2] x [col1, col2].
+    pub fn range_add(&mut self, row1: usize, col1: usize, row2: usize, col2: usize, val: T) {
+        let neg = T::zero() - val;
+        self.add_corner(row1, col1, val);
+        self.add_corner(row1, col2 + 1, neg);
+        self.add_corner(row2 + 1, col1, neg);
+        self.add_corner(row2 + 1, col2 + 1, val);
+    }
+
+    /// Sum of the rectangle [0, row] x [0, col].
+    pub fn sum(&self, row: i32, col: i32) -> T {
+        if row < 0 || col < 0 {
+            return T::zero();
+        }
+
+        let (x, y) = (row as usize + 1, col as usize + 1);
+        mul_usize(mul_usize(self.d.sum(row, col), x), y)
+            - mul_usize(self.di.sum(row, col), y)
+            - mul_usize(self.dj.sum(row, col), x)
+            + self.dij.sum(row, col)
+    }
+
+    /// Sum of the rectangle [row1, row2] x [col1, col2].
+    pub fn range_sum(&self, row1: i32, col1: i32, row2: i32, col2: i32) -> T {
+        self.sum(row2, col2) - self.sum(row1 - 1, col2) - self.sum(row2, col1 - 1)
+            + self.sum(row1 - 1, col1 - 1)
+    }
+}
+
+/// D-dimensional Fenwick tree with point add and box sum, O(log^D n) per operation.
+/// Coordinates are given in the same order as the dimensions passed to `new`.
+#[derive(Debug, Clone)]
+pub struct FenwickTreeND<T, const D: usize>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    dims: [usize; D],
+    strides: [usize; D],
+    tree: Vec<T>,
+}
+
+impl<T, const D: usize> FenwickTreeND<T, D>
+where
+    T: Copy + AddAssign + Sub<Output = T> + Zero,
+{
+    pub fn new(dims: [usize; D]) -> Self {
+        let mut strides = [1; D];
+        for d in (0..D.saturating_sub(1)).rev() {
+            strides[d] = strides[d + 1] * dims[d + 1];
+        }
+
+        FenwickTreeND {
+            dims,
+            strides,
+            tree: vec![T::zero(); dims.iter().product()],
+        }
+    }
+
+    pub fn add(&mut self, idx: [usize; D], val: T) {
+        self.add_internal(0, 0, &idx, val);
+    }
+
+    /// Sum of the box [0, idx[0]] x ... x [0, idx[D - 1]].
+    pub fn sum(&self, idx: [i64; D]) -> T {
+        if idx.iter().any(|&i| i < 0) {
+            return T::zero();
+        }
+
+        self.sum_internal(0, 0, &idx)
+    }
+
+    /// Sum of the box [lo[0], hi[0]] x ... x [lo[D - 1], hi[D - 1]],
+    /// inclusion-exclusion over the 2^D corners.
+    pub fn range_sum(&self, lo: [i64; D], hi: [i64; D]) -> T {
+        let mut res = T::zero();
+
+        for mask in 0..1usize << D {
+            let mut corner = hi;
+            for (d, c) in corner.iter_mut().enumerate() {
+                if mask >> d & 1 == 1 {
+                    *c = lo[d] - 1;
+                }
+            }
+
+            if mask.count_ones() % 2 == 0 {
+                res += self.sum(corner);
+            } else {
+                res = res - self.sum(corner);
+            }
+        }
+
+        res
+    }
+
+    fn add_internal(&mut self, d: usize, offset: usize, idx: &[usize; D], val: T) {
+        if d == D {
+            self.tree[offset] += val;
+            return;
+        }
+
+        let mut i = idx[d];
+        while i < self.dims[d] {
+            self.add_internal(d + 1, offset + i * self.strides[d], idx, val);
+            i |= i + 1;
+        }
+    }
+
+    fn sum_internal(&self, d: usize, offset: usize, idx: &[i64; D]) -> T {
+        if d == D {
+            return self.tree[offset];
+        }
+
+        let mut res = T::zero();
+        let mut i = idx[d];
+        while i >= 0 {
+            res += self.sum_internal(d + 1, offset + i as usize * self.strides[d], idx);
+            i = (i & (i + 1)) - 1;
+        }
+
+        res
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::cmp::min;
 
-    use super::{FenwickTree, FenwickTree2D, MinFenwickTree};
+    use crate::data_structures::monoid::{Gcd, Or, ProductMod, Xor};
+    use crate::data_structures::test_utils::{ModInt, MOD};
+
+    use super::{
+        FenwickTree, FenwickTree2D, FenwickTreeND, MaxFenwickTree, MinFenwickTree,
+        MonoidFenwickTree, OrderedMultiset, RangeFenwickTree, RangeFenwickTree2D,
+        RangeUpdateFenwickTree,
+    };
     use rand::prelude::*;
 
     #[test]
@@ -256,6 +680,215 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_fenwick_tree_from_vec() {
+        let mut rng = thread_rng();
+
+        for n in 0..70 {
+            let nums: Vec<u64> = (0..n).map(|_| rng.gen_range(0..1000)).collect();
+            let fenwick_tree = FenwickTree::from(nums.clone());
+
+            let mut prefix = 0;
+            for (i, &val) in nums.iter().enumerate() {
+                prefix += val;
+                assert_eq!(fenwick_tree.sum(i as i64), prefix);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fenwick_tree_from_iter_and_extend() {
+        let nums: Vec<f64> = (0..50).map(|i| i as f64 * 0.5).collect();
+        let mut fenwick_tree: FenwickTree<f64> = nums[..20].iter().copied().collect();
+        fenwick_tree.extend(nums[20..].iter().copied());
+
+        for l in 0..nums.len() {
+            for r in l..nums.len() {
+                let expected: f64 = nums[l..=r].iter().sum();
+                let sum = fenwick_tree.range_sum(l as i64, r as i64);
+                assert!((sum - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fenwick_tree_mod_int() {
+        let nums: Vec<u64> = (0..100).map(|i| (i * 987_654_321) % MOD).collect();
+        let mut fenwick_tree: FenwickTree<ModInt> = nums.iter().map(|&x| ModInt(x)).collect();
+        fenwick_tree.add(7, ModInt(MOD - 1));
+
+        let expected = nums[3..=60].iter().sum::<u64>() + MOD - 1;
+        assert_eq!(fenwick_tree.range_sum(3, 60), ModInt(expected % MOD));
+    }
+
+    #[test]
+    fn test_fenwick_tree_lower_bound() {
+        let n = 300;
+        let mut rng = thread_rng();
+        let nums: Vec<i64> = (0..n).map(|_| rng.gen_range(0..10)).collect();
+        let mut fenwick_tree = FenwickTree::new(n, 0);
+        for (i, &val) in nums.iter().enumerate() {
+            fenwick_tree.add(i, val);
+        }
+
+        let total: i64 = nums.iter().sum();
+        for target in 0..=total + 1 {
+            let mut prefix = 0;
+            let expected = nums
+                .iter()
+                .position(|&x| {
+                    prefix += x;
+                    prefix >= target
+                })
+                .unwrap_or(n);
+            assert_eq!(fenwick_tree.lower_bound(target), expected);
+        }
+
+        assert_eq!(FenwickTree::new(0, 0).lower_bound(1), 0);
+    }
+
+    #[test]
+    fn test_ordered_multiset() {
+        let mut set = OrderedMultiset::new(vec![50, 10, 30, 20, 40, 30]);
+        set.insert(&30);
+        set.insert(&10);
+        set.insert(&30);
+        set.insert(&50);
+
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.kth(0), Some(&10));
+        assert_eq!(set.kth(1), Some(&30));
+        assert_eq!(set.kth(2), Some(&30));
+        assert_eq!(set.kth(3), Some(&50));
+        assert_eq!(set.kth(4), None);
+
+        assert_eq!(set.count(&30), 2);
+        assert_eq!(set.count(&35), 0);
+        assert_eq!(set.count_less(&30), 1);
+        assert_eq!(set.count_less(&35), 3);
+        assert_eq!(set.rank(&50), 4);
+
+        assert!(set.remove(&30));
+        assert!(!set.remove(&20));
+        assert_eq!(set.kth(2), Some(&50));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn test_ordered_multiset_random() {
+        let mut rng = thread_rng();
+        let mut set = OrderedMultiset::new((0..100).collect());
+        let mut naive: Vec<i32> = Vec::new();
+
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..100);
+            if rng.gen_bool(0.6) {
+                set.insert(&key);
+                naive.push(key);
+                naive.sort();
+            } else {
+                let pos = naive.iter().position(|&x| x == key);
+                assert_eq!(set.remove(&key), pos.is_some());
+                if let Some(pos) = pos {
+                    naive.remove(pos);
+                }
+            }
+
+            let k = rng.gen_range(0..=naive.len());
+            assert_eq!(set.kth(k), naive.get(k));
+            assert_eq!(set.count_less(&key), naive.partition_point(|&x| x < key));
+        }
+    }
+
+    #[test]
+    fn test_range_update_fenwick_tree() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let mut nums: Vec<i64> = vec![0; n];
+        let mut fenwick_tree = RangeUpdateFenwickTree::new(n);
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let l = v1.min(v2);
+            let r = v1.max(v2);
+            let delta = rng.gen_range(-100..100);
+
+            fenwick_tree.range_add(l, r, delta);
+            nums[l..=r].iter_mut().for_each(|x| *x += delta);
+
+            let idx = rng.gen_range(0..n);
+            assert_eq!(fenwick_tree.get(idx), nums[idx]);
+        }
+    }
+
+    #[test]
+    fn test_range_fenwick_tree() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let mut nums: Vec<i64> = vec![0; n];
+        let mut fenwick_tree = RangeFenwickTree::new(n);
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let l = v1.min(v2);
+            let r = v1.max(v2);
+
+            if rng.gen_bool(0.5) {
+                let delta = rng.gen_range(-100..100);
+                fenwick_tree.range_add(l, r, delta);
+                nums[l..=r].iter_mut().for_each(|x| *x += delta);
+            } else {
+                let sum = fenwick_tree.range_sum(l as i64, r as i64);
+                assert_eq!(sum, nums[l..=r].iter().sum::<i64>());
+            }
+        }
+
+        assert_eq!(fenwick_tree.sum(-1), 0);
+        assert_eq!(fenwick_tree.sum(n as i64 - 1), nums.iter().sum::<i64>());
+    }
+
+    #[test]
+    fn test_range_fenwick_tree_unsigned() {
+        let n = 100;
+        let mut rng = thread_rng();
+        let mut nums: Vec<u64> = vec![0; n];
+        let mut point_tree = RangeUpdateFenwickTree::<u64>::new(n);
+        let mut range_tree = RangeFenwickTree::<u64>::new(n);
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (v1.min(v2), v1.max(v2));
+            let delta = rng.gen_range(0..100);
+
+            point_tree.range_add(l, r, delta);
+            range_tree.range_add(l, r, delta);
+            nums[l..=r].iter_mut().for_each(|x| *x += delta);
+
+            let idx = rng.gen_range(0..n);
+            assert_eq!(point_tree.get(idx), nums[idx]);
+            assert_eq!(
+                range_tree.range_sum(l as i64, r as i64),
+                nums[l..=r].iter().sum::<u64>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_range_fenwick_tree_large_values() {
+        let big = 5_000_000_000_000_000_000i64;
+        let mut fenwick_tree = RangeFenwickTree::new(3);
+        fenwick_tree.range_add(0, 0, big);
+        assert_eq!(fenwick_tree.range_sum(0, 0), big);
+        assert_eq!(fenwick_tree.sum(2), big);
+
+        let mut fenwick_tree_2d = RangeFenwickTree2D::with_shape(1, 1);
+        fenwick_tree_2d.range_add(0, 0, 0, 0, big);
+        assert_eq!(fenwick_tree_2d.sum(0, 0), big);
+    }
+
     #[test]
     fn test_min_fenwick_tree() {
         let n = 1000;
@@ -276,19 +909,104 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_monoid_fenwick_tree_prefix() {
+        let n = 300;
+        let mut rng = thread_rng();
+        let nums: Vec<u32> = (0..n).map(|_| rng.gen_range(1..1_000_000)).collect();
+
+        let mut max_tree = MaxFenwickTree::new(n);
+        let mut gcd_tree = MonoidFenwickTree::<Gcd<u32>>::new(n);
+        let mut or_tree = MonoidFenwickTree::<Or<u32>>::new(n);
+        for (i, &val) in nums.iter().enumerate() {
+            max_tree.update(i, val);
+            gcd_tree.update(i, val * 6);
+            or_tree.update(i, val);
+        }
+
+        for r in 0..n {
+            let prefix = &nums[..=r];
+            assert_eq!(max_tree.prefix(r as i64), *prefix.iter().max().unwrap());
+            assert_eq!(
+                gcd_tree.prefix(r as i64),
+                prefix
+                    .iter()
+                    .fold(0, |acc, &x| num::Integer::gcd(&acc, &(x * 6)))
+            );
+            assert_eq!(
+                or_tree.prefix(r as i64),
+                prefix.iter().fold(0, |acc, &x| acc | x)
+            );
+        }
+        assert_eq!(max_tree.prefix(-1), 0);
+    }
+
+    #[test]
+    fn test_monoid_fenwick_tree_product() {
+        const P: u64 = 998_244_353;
+        let n = 200;
+        let mut rng = thread_rng();
+        // zeros are valid values, a product has no range query as they cannot be divided out
+        let nums: Vec<u64> = (0..n)
+            .map(|_| {
+                if rng.gen_bool(0.05) {
+                    0
+                } else {
+                    rng.gen_range(1..P)
+                }
+            })
+            .collect();
+
+        let mut product_tree = MonoidFenwickTree::<ProductMod<P>>::new(n);
+        for (i, &val) in nums.iter().enumerate() {
+            product_tree.update(i, val);
+        }
+
+        for r in 0..n {
+            assert_eq!(
+                product_tree.prefix(r as i64),
+                nums[..=r].iter().fold(1, |acc, &x| acc * x % P)
+            );
+        }
+    }
+
+    #[test]
+    fn test_monoid_fenwick_tree_range() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let nums: Vec<u64> = (0..n).map(|_| rng.gen()).collect();
+
+        let mut xor_tree = MonoidFenwickTree::<Xor<u64>>::new(n);
+        for (i, &val) in nums.iter().enumerate() {
+            xor_tree.update(i, val);
+        }
+
+        for _ in 0..500 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (v1.min(v2), v1.max(v2));
+            let range = &nums[l..=r];
+
+            assert_eq!(
+                xor_tree.range(l as i64, r as i64),
+                range.iter().fold(0, |acc, &x| acc ^ x)
+            );
+        }
+    }
+
     #[test]
     fn test_fenwick_tree_2_d() {
         let n = 100;
-        let m = 100;
+        let m = 60;
         let mut rng = thread_rng();
         let mut matrix: Vec<Vec<i32>> = vec![vec![0; n]; m];
 
-        let mut fenwick_tree_2d = FenwickTree2D::new(n, m);
+        let mut fenwick_tree_2d = FenwickTree2D::with_shape(m, n);
 
         for i in 0..m {
             for j in 0..n {
                 matrix[i][j] = rng.gen_range(0..10);
-                fenwick_tree_2d.add(j, i, matrix[i][j]);
+                fenwick_tree_2d.point_add(i, j, matrix[i][j]);
             }
         }
 
@@ -308,16 +1026,16 @@ mod test {
     #[test]
     fn test_fenwick_tree_2_d_ranges() {
         let n = 5;
-        let m = 5;
+        let m = 8;
         let mut rng = thread_rng();
         let mut matrix: Vec<Vec<i32>> = vec![vec![0; n]; m];
 
-        let mut fenwick_tree_2d = FenwickTree2D::new(n, m);
+        let mut fenwick_tree_2d = FenwickTree2D::with_shape(m, n);
 
         for i in 0..m {
             for j in 0..n {
                 matrix[i][j] = rng.gen_range(0..10);
-                fenwick_tree_2d.add(j, i, matrix[i][j]);
+                fenwick_tree_2d.point_add(i, j, matrix[i][j]);
             }
         }
 
@@ -340,4 +1058,78 @@ mod test {
             assert_eq!(sum, expected);
         }
     }
+
+    #[test]
+    fn test_range_fenwick_tree_2_d() {
+        let rows = 12;
+        let cols = 17;
+        let mut rng = thread_rng();
+        let mut matrix: Vec<Vec<i64>> = vec![vec![0; cols]; rows];
+        let mut fenwick_tree_2d = RangeFenwickTree2D::with_shape(rows, cols);
+
+        for _ in 0..2000 {
+            let (rr1, rr2) = (rng.gen_range(0..rows), rng.gen_range(0..rows));
+            let (cc1, cc2) = (rng.gen_range(0..cols), rng.gen_range(0..cols));
+            let (r1, r2) = (rr1.min(rr2), rr1.max(rr2));
+            let (c1, c2) = (cc1.min(cc2), cc1.max(cc2));
+
+            if rng.gen_bool(0.5) {
+                let val = rng.gen_range(-10..10);
+                fenwick_tree_2d.range_add(r1, c1, r2, c2, val);
+                for row in matrix[r1..=r2].iter_mut() {
+                    row[c1..=c2].iter_mut().for_each(|x| *x += val);
+                }
+            } else {
+                let sum = fenwick_tree_2d.range_sum(r1 as i32, c1 as i32, r2 as i32, c2 as i32);
+                let expected = matrix[r1..=r2]
+                    .iter()
+                    .map(|row| row[c1..=c2].iter().sum::<i64>())
+                    .sum::<i64>();
+                assert_eq!(sum, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fenwick_tree_n_d() {
+        let dims = [4, 5, 6];
+        let mut rng = thread_rng();
+        let mut cube = vec![vec![vec![0i64; dims[2]]; dims[1]]; dims[0]];
+        let mut fenwick_tree = FenwickTreeND::new(dims);
+
+        for _ in 0..300 {
+            let idx = [
+                rng.gen_range(0..dims[0]),
+                rng.gen_range(0..dims[1]),
+                rng.gen_range(0..dims[2]),
+            ];
+            let val = rng.gen_range(-10..10);
+            cube[idx[0]][idx[1]][idx[2]] += val;
+            fenwick_tree.add(idx, val);
+        }
+
+        for _ in 0..300 {
+            let mut lo = [0i64; 3];
+            let mut hi = [0i64; 3];
+            for d in 0..3 {
+                let a = rng.gen_range(0..dims[d] as i64);
+                let b = rng.gen_range(0..dims[d] as i64);
+                lo[d] = a.min(b);
+                hi[d] = a.max(b);
+            }
+
+            let mut expected = 0;
+            for x in lo[0]..=hi[0] {
+                for y in lo[1]..=hi[1] {
+                    for z in lo[2]..=hi[2] {
+                        expected += cube[x as usize][y as usize][z as usize];
+                    }
+                }
+            }
+
+            assert_eq!(fenwick_tree.range_sum(lo, hi), expected);
+        }
+
+        assert_eq!(fenwick_tree.sum([-1, 3, 3]), 0);
+    }
 }
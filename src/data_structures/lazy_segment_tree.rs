@@ -0,0 +1,435 @@
+use crate::data_structures::monoid::{Max, Min, Monoid, Sum};
+use crate::data_structures::segment_tree::{l, r};
+use num::{Bounded, FromPrimitive, One, Zero};
+use std::cmp::{max, min};
+use std::marker::PhantomData;
+use std::ops::{Add, Mul};
+
+/// Action of a lazy tag on the aggregate of a segment.
+/// Tags have to form a monoid under `compose` and `apply` has to distribute over
+/// `M::combine`, so that a pending tag can be pushed down to the children at any time.
+pub trait Action<M: Monoid> {
+    type F: Clone;
+
+    fn identity() -> Self::F;
+
+    /// Tag equivalent to applying `g` first and `f` afterwards.
+    fn compose(f: &Self::F, g: &Self::F) -> Self::F;
+
+    /// Applies the tag to the aggregate `x` of a segment with `len` elements.
+    fn apply(f: &Self::F, x: &M::S, len: usize) -> M::S;
+}
+
+/// a[i] += f
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RangeAdd<T>(PhantomData<T>);
+
+/// a[i] = f
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RangeAssign<T>(PhantomData<T>);
+
+/// a[i] = f.0 * a[i] + f.1
+/// For min/max aggregates the multiplier has to be non-negative.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RangeAffine<T>(PhantomData<T>);
+
+fn len_as<T: FromPrimitive>(len: usize) -> T {
+    T::from_usize(len).expect("segment length does not fit into the value type")
+}
+
+impl<T> Action<Sum<T>> for RangeAdd<T>
+where
+    T: Copy + Zero + Add<Output = T> + Mul<Output = T> + FromPrimitive,
+{
+    type F = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn compose(f: &T, g: &T) -> T {
+        *f + *g
+    }
+
+    fn apply(f: &T, x: &T, len: usize) -> T {
+        *x + *f * len_as::<T>(len)
+    }
+}
+
+impl<T> Action<Sum<T>> for RangeAssign<T>
+where
+    T: Copy + Zero + Add<Output = T> + Mul<Output = T> + FromPrimitive,
+{
+    type F = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn compose(f: &Option<T>, g: &Option<T>) -> Option<T> {
+        f.or(*g)
+    }
+
+    fn apply(f: &Option<T>, x: &T, len: usize) -> T {
+        match f {
+            Some(val) => *val * len_as::<T>(len),
+            None => *x,
+        }
+    }
+}
+
+impl<T> Action<Sum<T>> for RangeAffine<T>
+where
+    T: Copy + Zero + One + Add<Output = T> + Mul<Output = T> + FromPrimitive,
+{
+    type F = (T, T);
+
+    fn identity() -> (T, T) {
+        (T::one(), T::zero())
+    }
+
+    fn compose(f: &(T, T), g: &(T, T)) -> (T, T) {
+        (f.0 * g.0, f.0 * g.1 + f.1)
+    }
+
+    fn apply(f: &(T, T), x: &T, len: usize) -> T {
+        f.0 * *x + f.1 * len_as::<T>(len)
+    }
+}
+
+macro_rules! impl_extremum_actions {
+    ($monoid:ident) => {
+        impl<T> Action<$monoid<T>> for RangeAdd<T>
+        where
+            T: Copy + Ord + Bounded + Zero + Add<Output = T>,
+        {
+            type F = T;
+
+            fn identity() -> T {
+                T::zero()
+            }
+
+            fn compose(f: &T, g: &T) -> T {
+                *f + *g
+            }
+
+            fn apply(f: &T, x: &T, _len: usize) -> T {
+                *x + *f
+            }
+        }
+
+        impl<T> Action<$monoid<T>> for RangeAssign<T>
+        where
+            T: Copy + Ord + Bounded,
+        {
+            type F = Option<T>;
+
+            fn identity() -> Option<T> {
+                None
+            }
+
+            fn compose(f: &Option<T>, g: &Option<T>) -> Option<T> {
+                f.or(*g)
+            }
+
+            fn apply(f: &Option<T>, x: &T, _len: usize) -> T {
+                f.unwrap_or(*x)
+            }
+        }
+
+        impl<T> Action<$monoid<T>> for RangeAffine<T>
+        where
+            T: Copy + Ord + Bounded + Zero + One + Add<Output = T> + Mul<Output = T>,
+        {
+            type F = (T, T);
+
+            fn identity() -> (T, T) {
+                (T::one(), T::zero())
+            }
+
+            fn compose(f: &(T, T), g: &(T, T)) -> (T, T) {
+                (f.0 * g.0, f.0 * g.1 + f.1)
+            }
+
+            fn apply(f: &(T, T), x: &T, _len: usize) -> T {
+                f.0 * *x + f.1
+            }
+        }
+    };
+}
+
+impl_extremum_actions!(Min);
+impl_extremum_actions!(Max);
+
+/// Lazy Segment Tree
+/// O(log n) time complexity for range queries and range updates.
+/// Nodes store the aggregate of their segment with all tags above them already applied,
+/// while `lazy` keeps the tag that still has to be pushed down to the children.
+#[derive(Debug, Clone)]
+pub struct LazySegmentTree<M: Monoid, A: Action<M>> {
+    n: usize,
+    st: Vec<M::S>,
+    lazy: Vec<A::F>,
+}
+
+impl<M: Monoid, A: Action<M>> LazySegmentTree<M, A> {
+    pub fn new(nums: Vec<M::S>) -> Self {
+        let n = nums.len();
+
+        let mut segment_tree = LazySegmentTree {
+            n,
+            st: vec![M::identity(); 4 * n],
+            lazy: vec![A::identity(); 4 * n],
+        };
+
+        if n > 0 {
+            segment_tree.build_internal(1, 0, n - 1, &nums);
+        }
+
+        segment_tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Aggregate of the values in the inclusive range [i, j].
+    /// Returns the identity for an empty range.
+    pub fn query(&mut self, i: usize, j: usize) -> M::S {
+        if self.n == 0 {
+            return M::identity();
+        }
+
+        self.query_internal(1, 0, self.n - 1, i, min(j, self.n - 1))
+    }
+
+    /// Applies the tag `f` to every value in the inclusive range [i, j].
+    pub fn update(&mut self, i: usize, j: usize, f: A::F) {
+        if self.n == 0 {
+            return;
+        }
+
+        self.update_internal(1, 0, self.n - 1, i, min(j, self.n - 1), &f);
+    }
+
+    pub fn get(&mut self, i: usize) -> M::S {
+        self.query(i, i)
+    }
+
+    pub fn set(&mut self, i: usize, val: M::S) {
+        self.set_internal(1, 0, self.n - 1, i, val);
+    }
+
+    fn build_internal(&mut self, p: usize, left: usize, right: usize, nums: &[M::S]) {
+        if left == right {
+            self.st[p] = nums[left].clone();
+        } else {
+            let m = (right - left) / 2 + left;
+            self.build_internal(l(p), left, m, nums);
+            self.build_internal(r(p), m + 1, right, nums);
+            self.pull(p);
+        }
+    }
+
+    fn pull(&mut self, p: usize) {
+        self.st[p] = M::combine(&self.st[l(p)], &self.st[r(p)]);
+    }
+
+    fn apply_node(&mut self, p: usize, len: usize, f: &A::F) {
+        self.st[p] = A::apply(f, &self.st[p], len);
+        self.lazy[p] = A::compose(f, &self.lazy[p]);
+    }
+
+    fn propagate(&mut self, p: usize, left: usize, right: usize) {
+        if left == right {
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        let f = std::mem::replace(&mut self.lazy[p], A::identity());
+        self.apply_node(l(p), m - left + 1, &f);
+        self.apply_node(r(p), right - m, &f);
+    }
+
+    fn update_internal(
+        &mut self,
+        p: usize,
+        left: usize,
+        right: usize,
+        i: usize,
+        j: usize,
+        f: &A::F,
+    ) {
+        if i > j {
+            return;
+        }
+
+        if left >= i && right <= j {
+            self.apply_node(p, right - left + 1, f);
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        self.update_internal(l(p), left, m, i, min(m, j), f);
+        self.update_internal(r(p), m + 1, right, max(i, m + 1), j, f);
+        self.pull(p);
+    }
+
+    fn set_internal(&mut self, p: usize, left: usize, right: usize, i: usize, val: M::S) {
+        if left == right {
+            self.st[p] = val;
+            self.lazy[p] = A::identity();
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        if i <= m {
+            self.set_internal(l(p), left, m, i, val);
+        } else {
+            self.set_internal(r(p), m + 1, right, i, val);
+        }
+        self.pull(p);
+    }
+
+    fn query_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize) -> M::S {
+        if i > j {
+            return M::identity();
+        }
+
+        if left >= i && right <= j {
+            return self.st[p].clone();
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        let res_left = self.query_internal(l(p), left, m, i, min(m, j));
+        let res_right = self.query_internal(r(p), m + 1, right, max(i, m + 1), j);
+        M::combine(&res_left, &res_right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, LazySegmentTree, RangeAdd, RangeAffine, RangeAssign};
+    use crate::data_structures::monoid::{Max, Min, Monoid, Sum};
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    fn random_range(rng: &mut ThreadRng, n: usize) -> (usize, usize) {
+        let v1 = rng.gen_range(0..n);
+        let v2 = rng.gen_range(0..n);
+        (min(v1, v2), max(v1, v2))
+    }
+
+    /// Runs random updates and queries against a naive array.
+    fn check_against_naive<M, A>(
+        gen_tag: impl Fn(&mut ThreadRng) -> A::F,
+        apply_naive: impl Fn(&A::F, i64) -> i64,
+    ) where
+        M: Monoid<S = i64>,
+        A: Action<M>,
+    {
+        let n = 200;
+        let mut rng = thread_rng();
+        let mut nums: Vec<i64> = (0..n).map(|_| rng.gen_range(-100..100)).collect();
+        let mut st = LazySegmentTree::<M, A>::new(nums.clone());
+
+        for _ in 0..5000 {
+            let (l, r) = random_range(&mut rng, n);
+            match rng.gen_range(0..3) {
+                0 => {
+                    let f = gen_tag(&mut rng);
+                    for num in nums[l..=r].iter_mut() {
+                        *num = apply_naive(&f, *num);
+                    }
+                    st.update(l, r, f);
+                }
+                1 => {
+                    let val = rng.gen_range(-100..100);
+                    nums[l] = val;
+                    st.set(l, val);
+                }
+                _ => {
+                    let expected = nums[l..=r]
+                        .iter()
+                        .fold(M::identity(), |acc, x| M::combine(&acc, x));
+                    assert_eq!(st.query(l, r), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_sum_add() {
+        check_against_naive::<Sum<i64>, RangeAdd<i64>>(
+            |rng| rng.gen_range(-100..100),
+            |f, x| x + f,
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_sum_assign() {
+        check_against_naive::<Sum<i64>, RangeAssign<i64>>(
+            |rng| Some(rng.gen_range(-100..100)),
+            |f, x| f.unwrap_or(x),
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_sum_affine() {
+        check_against_naive::<Sum<i64>, RangeAffine<i64>>(
+            |rng| (rng.gen_range(-1..=1), rng.gen_range(-100..100)),
+            |f, x| f.0 * x + f.1,
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_min_add() {
+        check_against_naive::<Min<i64>, RangeAdd<i64>>(
+            |rng| rng.gen_range(-100..100),
+            |f, x| x + f,
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_max_assign() {
+        check_against_naive::<Max<i64>, RangeAssign<i64>>(
+            |rng| Some(rng.gen_range(-100..100)),
+            |f, x| f.unwrap_or(x),
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_min_affine() {
+        check_against_naive::<Min<i64>, RangeAffine<i64>>(
+            |rng| (rng.gen_range(0..=1), rng.gen_range(-100..100)),
+            |f, x| f.0 * x + f.1,
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_max_affine() {
+        check_against_naive::<Max<i64>, RangeAffine<i64>>(
+            |rng| (rng.gen_range(0..=1), rng.gen_range(-100..100)),
+            |f, x| f.0 * x + f.1,
+        );
+    }
+
+    #[test]
+    fn test_lazy_segment_tree_assign_then_add() {
+        let mut st = LazySegmentTree::<Sum<i64>, RangeAffine<i64>>::new(vec![1, 2, 3, 4, 5]);
+        // assign 10 on [1, 3], then add 1 on [0, 2]
+        st.update(1, 3, (0, 10));
+        st.update(0, 2, (1, 1));
+
+        assert_eq!(st.query(0, 4), 2 + 11 + 11 + 10 + 5);
+        assert_eq!(st.get(1), 11);
+        assert_eq!(st.get(3), 10);
+    }
+}
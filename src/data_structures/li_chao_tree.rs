@@ -0,0 +1,468 @@
+use crate::data_structures::monoid::{Idempotent, Max, Min, Monoid};
+use crate::data_structures::segment_tree::{l, r};
+use std::cmp::{max, min};
+
+// y = kx + b, kept in i128 so evaluating a line at any i64 coordinate cannot overflow
+pub(crate) type Line = (i128, i128);
+
+// slopes, intercepts and points are limited to i64, as |kx + b| of u64 values overflows i128
+#[inline]
+pub(crate) fn widen<T: Into<i64>>(x: T) -> i128 {
+    let x: i64 = x.into();
+    x as i128
+}
+
+#[inline]
+pub(crate) fn eval(line: &Line, x: i128) -> i128 {
+    line.0 * x + line.1
+}
+
+// whether `a` is strictly better than `b`, min or max depending on the monoid
+#[inline]
+pub(crate) fn better<M: Monoid<S = i128>>(a: i128, b: i128) -> bool {
+    a != b && M::combine(&a, &b) == a
+}
+
+/// Li Chao Tree
+/// Set of lines y = kx + b answering the minimum (or maximum, depending on `M`) of all
+/// lines at a point. The points are fixed up front and every node of the segment tree over
+/// them keeps the line that is best at its midpoint, so the best line at a point is on the
+/// path from the root to its leaf.
+/// Inserting a line is O(log n), a line restricted to a segment O(log^2 n), a query O(log n).
+/// Values are computed in i128, so they are exact for slopes, intercepts and points of any
+/// integer type that converts into `i64`. `u64` is rejected, as k * x of two `u64` values
+/// does not fit into i128:
+///
+/// ```compile_fail
+/// use cp_rust::data_structures::li_chao_tree::MinLiChaoTree;
+///
+/// let mut tree = MinLiChaoTree::new(vec![0u64, u64::MAX]);
+/// tree.add_line(u64::MAX, u64::MAX);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LiChaoTree<T, M> {
+    xs: Vec<T>,
+    st: Vec<Option<Line>>,
+    _monoid: std::marker::PhantomData<M>,
+}
+
+pub type MinLiChaoTree<T> = LiChaoTree<T, Min<i128>>;
+pub type MaxLiChaoTree<T> = LiChaoTree<T, Max<i128>>;
+
+impl<T, M> LiChaoTree<T, M>
+where
+    T: Copy + Ord + Into<i64>,
+    M: Idempotent<S = i128>,
+{
+    /// Tree over the given query points, duplicates are ignored.
+    pub fn new(mut xs: Vec<T>) -> Self {
+        xs.sort();
+        xs.dedup();
+        let n = xs.len();
+
+        LiChaoTree {
+            xs,
+            st: vec![None; 4 * n.max(1)],
+            _monoid: std::marker::PhantomData,
+        }
+    }
+
+    pub fn add_line(&mut self, k: T, b: T) {
+        if self.xs.is_empty() {
+            return;
+        }
+
+        let line = (widen(k), widen(b));
+        self.insert_internal(1, 0, self.xs.len() - 1, line);
+    }
+
+    /// Adds the line restricted to the points in the inclusive range [x1, x2].
+    pub fn add_segment(&mut self, k: T, b: T, x1: T, x2: T) {
+        let i = self.xs.partition_point(|&x| x < x1);
+        let j = self.xs.partition_point(|&x| x <= x2);
+        if i >= j {
+            return;
+        }
+
+        let line = (widen(k), widen(b));
+        self.segment_internal(1, 0, self.xs.len() - 1, i, j - 1, line);
+    }
+
+    /// Best value of all lines at `x`, `None` if no line covers it.
+    /// `x` has to be one of the points the tree was built with.
+    pub fn query(&self, x: T) -> Option<i128> {
+        let i = self
+            .xs
+            .binary_search(&x)
+            .expect("query point has to be one of the initial points");
+
+        let x = widen(x);
+        let (mut p, mut left, mut right) = (1, 0, self.xs.len() - 1);
+        let mut res: Option<i128> = None;
+        loop {
+            if let Some(line) = &self.st[p] {
+                let y = eval(line, x);
+                res = Some(res.map_or(y, |res| M::combine(&res, &y)));
+            }
+
+            if left == right {
+                return res;
+            }
+
+            let m = (right - left) / 2 + left;
+            if i <= m {
+                p = l(p);
+                right = m;
+            } else {
+                p = r(p);
+                left = m + 1;
+            }
+        }
+    }
+
+    fn insert_internal(&mut self, p: usize, left: usize, right: usize, line: Line) {
+        let Some(cur) = self.st[p] else {
+            self.st[p] = Some(line);
+            return;
+        };
+
+        let m = (right - left) / 2 + left;
+        let (xl, xm, xr) = (
+            widen(self.xs[left]),
+            widen(self.xs[m]),
+            widen(self.xs[right]),
+        );
+
+        // keep the line that wins at the midpoint, the loser can only win on one side
+        let (keep, push) = if better::<M>(eval(&line, xm), eval(&cur, xm)) {
+            (line, cur)
+        } else {
+            (cur, line)
+        };
+        self.st[p] = Some(keep);
+
+        if left == right {
+            return;
+        }
+
+        if better::<M>(eval(&push, xl), eval(&keep, xl)) {
+            self.insert_internal(l(p), left, m, push);
+        } else if better::<M>(eval(&push, xr), eval(&keep, xr)) {
+            self.insert_internal(r(p), m + 1, right, push);
+        }
+    }
+
+    fn segment_internal(
+        &mut self,
+        p: usize,
+        left: usize,
+        right: usize,
+        i: usize,
+        j: usize,
+        line: Line,
+    ) {
+        if i > j {
+            return;
+        }
+
+        if (left >= i) && (right <= j) {
+            self.insert_internal(p, left, right, line);
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        self.segment_internal(l(p), left, m, i, min(m, j), line);
+        self.segment_internal(r(p), m + 1, right, max(i, m + 1), j, line);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    left: usize,
+    right: usize,
+    line: Option<Line>,
+}
+
+/// Dynamic Li Chao Tree
+/// Li Chao tree over all coordinates [lo, hi] of `i64`, nodes are created only on the paths
+/// of inserted lines, so no query points have to be known up front.
+/// O(log (hi - lo)) per line and query, O(log^2 (hi - lo)) per segment.
+#[derive(Debug, Clone)]
+pub struct DynamicLiChaoTree<T, M> {
+    lo: i64,
+    hi: i64,
+    nodes: Vec<Node>,
+    _marker: std::marker::PhantomData<(T, M)>,
+}
+
+pub type MinDynamicLiChaoTree<T> = DynamicLiChaoTree<T, Min<i128>>;
+pub type MaxDynamicLiChaoTree<T> = DynamicLiChaoTree<T, Max<i128>>;
+
+#[inline]
+fn mid(left: i64, right: i64) -> i64 {
+    ((left as i128 + right as i128).div_euclid(2)) as i64
+}
+
+impl<T, M> DynamicLiChaoTree<T, M>
+where
+    T: Copy + Into<i64>,
+    M: Idempotent<S = i128>,
+{
+    pub fn new(lo: i64, hi: i64) -> Self {
+        assert!(lo <= hi, "empty coordinate range");
+
+        // the root is node 0, so 0 marks a missing child
+        DynamicLiChaoTree {
+            lo,
+            hi,
+            nodes: vec![Node {
+                left: 0,
+                right: 0,
+                line: None,
+            }],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Number of allocated nodes.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn add_line(&mut self, k: T, b: T) {
+        self.insert_internal(0, self.lo, self.hi, (widen(k), widen(b)));
+    }
+
+    /// Adds the line restricted to the inclusive range [x1, x2].
+    pub fn add_segment(&mut self, k: T, b: T, x1: i64, x2: i64) {
+        let (x1, x2) = (max(x1, self.lo), min(x2, self.hi));
+        if x1 > x2 {
+            return;
+        }
+
+        self.segment_internal(0, self.lo, self.hi, x1, x2, (widen(k), widen(b)));
+    }
+
+    /// Best value of all lines at `x`, `None` if no line covers it.
+    pub fn query(&self, x: i64) -> Option<i128> {
+        if x < self.lo || x > self.hi {
+            return None;
+        }
+
+        let (mut p, mut left, mut right) = (0, self.lo, self.hi);
+        let mut res: Option<i128> = None;
+        loop {
+            if let Some(line) = &self.nodes[p].line {
+                let y = eval(line, x as i128);
+                res = Some(res.map_or(y, |res| M::combine(&res, &y)));
+            }
+
+            let m = mid(left, right);
+            let next = if x <= m {
+                right = m;
+                self.nodes[p].left
+            } else {
+                left = m + 1;
+                self.nodes[p].right
+            };
+
+            if next == 0 {
+                return res;
+            }
+            p = next;
+        }
+    }
+
+    fn child(&mut self, p: usize, go_left: bool) -> usize {
+        let c = if go_left {
+            self.nodes[p].left
+        } else {
+            self.nodes[p].right
+        };
+        if c != 0 {
+            return c;
+        }
+
+        self.nodes.push(Node {
+            left: 0,
+            right: 0,
+            line: None,
+        });
+        let c = self.nodes.len() - 1;
+        if go_left {
+            self.nodes[p].left = c;
+        } else {
+            self.nodes[p].right = c;
+        }
+
+        c
+    }
+
+    fn insert_internal(&mut self, p: usize, left: i64, right: i64, line: Line) {
+        let Some(cur) = self.nodes[p].line else {
+            self.nodes[p].line = Some(line);
+            return;
+        };
+
+        let m = mid(left, right);
+        let (xl, xm, xr) = (left as i128, m as i128, right as i128);
+
+        let (keep, push) = if better::<M>(eval(&line, xm), eval(&cur, xm)) {
+            (line, cur)
+        } else {
+            (cur, line)
+        };
+        self.nodes[p].line = Some(keep);
+
+        if left == right {
+            return;
+        }
+
+        if better::<M>(eval(&push, xl), eval(&keep, xl)) {
+            let c = self.child(p, true);
+            self.insert_internal(c, left, m, push);
+        } else if better::<M>(eval(&push, xr), eval(&keep, xr)) {
+            let c = self.child(p, false);
+            self.insert_internal(c, m + 1, right, push);
+        }
+    }
+
+    fn segment_internal(&mut self, p: usize, left: i64, right: i64, i: i64, j: i64, line: Line) {
+        if i > j {
+            return;
+        }
+
+        if left >= i && right <= j {
+            self.insert_internal(p, left, right, line);
+            return;
+        }
+
+        let m = mid(left, right);
+        if i <= min(m, j) {
+            let c = self.child(p, true);
+            self.segment_internal(c, left, m, i, min(m, j), line);
+        }
+        if max(i, m + 1) <= j {
+            let c = self.child(p, false);
+            self.segment_internal(c, m + 1, right, max(i, m + 1), j, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaxDynamicLiChaoTree, MaxLiChaoTree, MinDynamicLiChaoTree, MinLiChaoTree};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_li_chao_tree() {
+        let mut tree = MinLiChaoTree::new((-5..=5).collect());
+        assert_eq!(tree.query(0), None);
+
+        tree.add_line(1, 0);
+        tree.add_line(-1, 0);
+        tree.add_segment(0, -10, -1, 1);
+        assert_eq!(tree.query(-3), Some(-3));
+        assert_eq!(tree.query(4), Some(-4));
+        assert_eq!(tree.query(1), Some(-10));
+        assert_eq!(tree.query(2), Some(-2));
+
+        let mut tree = MaxLiChaoTree::new(vec![0u32, 10, 100]);
+        tree.add_line(2, 5);
+        tree.add_line(0, 30);
+        assert_eq!(tree.query(0), Some(30));
+        assert_eq!(tree.query(100), Some(205));
+    }
+
+    #[test]
+    fn test_li_chao_tree_random() {
+        let mut rng = thread_rng();
+        let xs: Vec<i64> = (0..200).map(|_| rng.gen_range(-1000..1000)).collect();
+        let mut min_tree = MinLiChaoTree::new(xs.clone());
+        let mut max_tree = MaxLiChaoTree::new(xs.clone());
+        let mut lines = Vec::new();
+
+        for _ in 0..300 {
+            let (k, b) = (rng.gen_range(-50..50), rng.gen_range(-10000..10000));
+            let (x1, x2) = if rng.gen_bool(0.5) {
+                (i64::MIN, i64::MAX)
+            } else {
+                let (a, c) = (rng.gen_range(-1000..1000), rng.gen_range(-1000..1000));
+                (a.min(c), a.max(c))
+            };
+            min_tree.add_segment(k, b, x1, x2);
+            max_tree.add_segment(k, b, x1, x2);
+            lines.push((k, b, x1, x2));
+
+            let x = *xs.choose(&mut rng).unwrap();
+            let values = lines
+                .iter()
+                .filter(|&&(_, _, x1, x2)| x1 <= x && x <= x2)
+                .map(|&(k, b, _, _)| (k * x + b) as i128);
+            assert_eq!(min_tree.query(x), values.clone().min());
+            assert_eq!(max_tree.query(x), values.max());
+        }
+    }
+
+    #[test]
+    fn test_dynamic_li_chao_tree_overflow() {
+        let mut tree = MaxDynamicLiChaoTree::new(i64::MIN, i64::MAX);
+        tree.add_line(i64::MAX, i64::MAX);
+        tree.add_line(i64::MIN, 0);
+
+        let big = i64::MAX as i128;
+        assert_eq!(tree.query(i64::MAX), Some(big * big + big));
+        let small = i64::MIN as i128;
+        assert_eq!(tree.query(i64::MIN), Some(small * small));
+        assert_eq!(tree.query(0), Some(big));
+    }
+
+    #[test]
+    fn test_li_chao_tree_extreme_values() {
+        let big = u32::MAX as i128;
+        let mut tree = MaxLiChaoTree::new(vec![0, 1, u32::MAX]);
+        tree.add_line(u32::MAX, u32::MAX);
+        tree.add_line(0, u32::MAX - 1);
+        assert_eq!(tree.query(u32::MAX), Some(big * big + big));
+        assert_eq!(tree.query(0), Some(big));
+
+        let mut tree = MinLiChaoTree::new(vec![i64::MIN, 0, i64::MAX]);
+        tree.add_line(i64::MAX, i64::MIN);
+        tree.add_line(i64::MIN, i64::MIN);
+        let (big, small) = (i64::MAX as i128, i64::MIN as i128);
+        assert_eq!(tree.query(i64::MIN), Some(small * big + small));
+        assert_eq!(tree.query(i64::MAX), Some(small * big + small));
+        assert_eq!(tree.query(0), Some(small));
+    }
+
+    #[test]
+    fn test_dynamic_li_chao_tree_random() {
+        let mut rng = thread_rng();
+        let (lo, hi) = (-1_000_000_000_000i64, 1_000_000_000_000i64);
+        let mut tree = MinDynamicLiChaoTree::new(lo, hi);
+        let mut lines = Vec::new();
+
+        for _ in 0..500 {
+            let k = rng.gen_range(-1_000_000i64..1_000_000);
+            let b = rng.gen_range(-1_000_000_000_000_000i64..1_000_000_000_000_000);
+            let (a, c) = (rng.gen_range(lo..=hi), rng.gen_range(lo..=hi));
+            let (x1, x2) = (a.min(c), a.max(c));
+            if rng.gen_bool(0.5) {
+                tree.add_line(k, b);
+                lines.push((k, b, lo, hi));
+            } else {
+                tree.add_segment(k, b, x1, x2);
+                lines.push((k, b, x1, x2));
+            }
+
+            for x in [rng.gen_range(lo..=hi), x1, x2] {
+                let expected = lines
+                    .iter()
+                    .filter(|&&(_, _, x1, x2)| x1 <= x && x <= x2)
+                    .map(|&(k, b, _, _)| k as i128 * x as i128 + b as i128)
+                    .min();
+                assert_eq!(tree.query(x), expected);
+            }
+        }
+    }
+}
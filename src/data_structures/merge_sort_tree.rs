@@ -0,0 +1,206 @@
+use crate::data_structures::segment_tree::{l, r};
+use std::cmp::{max, min};
+
+/// Merge Sort Tree
+/// Segment tree where every node keeps the sorted values of its range, O(n log n) memory.
+/// Counting the values `<= x` in a range binary searches the O(log n) covering nodes,
+/// O(log^2 n) per query.
+/// With fractional cascading every node also stores, for every prefix of its sorted values,
+/// how many of them come from the left child. A single binary search at the root is then
+/// carried down to the children in O(1), O(log n) per query.
+#[derive(Debug, Clone)]
+pub struct MergeSortTree<T: Ord + Clone> {
+    n: usize,
+    st: Vec<Vec<T>>,
+    // left_count[p][k] is the number of values from the left child among the first k of node p
+    left_count: Option<Vec<Vec<usize>>>,
+}
+
+impl<T: Ord + Clone> MergeSortTree<T> {
+    pub fn new(nums: &[T]) -> Self {
+        Self::build(nums, false)
+    }
+
+    /// Builds the tree with fractional cascading, O(log n) per query.
+    pub fn with_cascading(nums: &[T]) -> Self {
+        Self::build(nums, true)
+    }
+
+    fn build(nums: &[T], cascading: bool) -> Self {
+        let n = nums.len();
+        let mut tree = MergeSortTree {
+            n,
+            st: vec![Vec::new(); 4 * n],
+            left_count: cascading.then(|| vec![Vec::new(); 4 * n]),
+        };
+
+        if n > 0 {
+            tree.build_internal(nums, 1, 0, n - 1);
+        }
+
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Number of values `<= x` in the inclusive range [i, j].
+    pub fn count_le(&self, i: usize, j: usize, x: &T) -> usize {
+        if i > j || self.n == 0 {
+            return 0;
+        }
+
+        let j = min(j, self.n - 1);
+        if self.left_count.is_some() {
+            let k = self.st[1].partition_point(|v| v <= x);
+            self.cascade_internal(1, 0, self.n - 1, i, j, k)
+        } else {
+            self.count_internal(1, 0, self.n - 1, i, j, x)
+        }
+    }
+
+    /// Number of values `< x` in the inclusive range [i, j].
+    pub fn count_lt(&self, i: usize, j: usize, x: &T) -> usize {
+        if i > j || self.n == 0 {
+            return 0;
+        }
+
+        // the values `< x` are exactly the values `<=` the largest stored value below `x`
+        let j = min(j, self.n - 1);
+        let k = self.st[1].partition_point(|v| v < x);
+        if k == 0 {
+            return 0;
+        }
+
+        self.count_le(i, j, &self.st[1][k - 1])
+    }
+
+    fn build_internal(&mut self, nums: &[T], p: usize, left: usize, right: usize) {
+        if left == right {
+            self.st[p] = vec![nums[left].clone()];
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        self.build_internal(nums, l(p), left, m);
+        self.build_internal(nums, r(p), m + 1, right);
+
+        let (a, b) = (&self.st[l(p)], &self.st[r(p)]);
+        let mut merged = Vec::with_capacity(a.len() + b.len());
+        let mut counts = vec![0];
+        let (mut x, mut y) = (0, 0);
+        while x < a.len() || y < b.len() {
+            if y == b.len() || (x < a.len() && a[x] <= b[y]) {
+                merged.push(a[x].clone());
+                x += 1;
+            } else {
+                merged.push(b[y].clone());
+                y += 1;
+            }
+            counts.push(x);
+        }
+
+        self.st[p] = merged;
+        if let Some(left_count) = &mut self.left_count {
+            left_count[p] = counts;
+        }
+    }
+
+    fn count_internal(
+        &self,
+        p: usize,
+        left: usize,
+        right: usize,
+        i: usize,
+        j: usize,
+        x: &T,
+    ) -> usize {
+        if i > j {
+            return 0;
+        }
+
+        if (left >= i) && (right <= j) {
+            return self.st[p].partition_point(|v| v <= x);
+        }
+
+        let m = (right - left) / 2 + left;
+        self.count_internal(l(p), left, m, i, min(m, j), x)
+            + self.count_internal(r(p), m + 1, right, max(i, m + 1), j, x)
+    }
+
+    // `k` is the number of values `<= x` in node p
+    fn cascade_internal(
+        &self,
+        p: usize,
+        left: usize,
+        right: usize,
+        i: usize,
+        j: usize,
+        k: usize,
+    ) -> usize {
+        if i > j || k == 0 {
+            return 0;
+        }
+
+        if (left >= i) && (right <= j) {
+            return k;
+        }
+
+        let m = (right - left) / 2 + left;
+        let k_left = self.left_count.as_ref().unwrap()[p][k];
+        self.cascade_internal(l(p), left, m, i, min(m, j), k_left)
+            + self.cascade_internal(r(p), m + 1, right, max(i, m + 1), j, k - k_left)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeSortTree;
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    #[test]
+    fn test_merge_sort_tree() {
+        let nums = [5, 1, 4, 1, 3, 9, 2, 6];
+        for tree in [
+            MergeSortTree::new(&nums),
+            MergeSortTree::with_cascading(&nums),
+        ] {
+            assert_eq!(tree.len(), 8);
+            assert_eq!(tree.count_le(0, 7, &4), 5);
+            assert_eq!(tree.count_le(2, 5, &3), 2);
+            assert_eq!(tree.count_lt(2, 5, &3), 1);
+            assert_eq!(tree.count_le(0, 7, &0), 0);
+            assert_eq!(tree.count_le(0, 7, &100), 8);
+            assert_eq!(tree.count_le(5, 2, &100), 0);
+        }
+    }
+
+    #[test]
+    fn test_merge_sort_tree_random() {
+        let n = 300;
+        let mut rng = thread_rng();
+        let nums: Vec<i32> = (0..n).map(|_| rng.gen_range(-30..30)).collect();
+        let tree = MergeSortTree::new(&nums);
+        let cascading = MergeSortTree::with_cascading(&nums);
+
+        for _ in 0..2000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (i, j) = (min(v1, v2), max(v1, v2));
+            let x = rng.gen_range(-35..35);
+
+            let le = nums[i..=j].iter().filter(|&&v| v <= x).count();
+            let lt = nums[i..=j].iter().filter(|&&v| v < x).count();
+            assert_eq!(tree.count_le(i, j, &x), le);
+            assert_eq!(cascading.count_le(i, j, &x), le);
+            assert_eq!(tree.count_lt(i, j, &x), lt);
+            assert_eq!(cascading.count_lt(i, j, &x), lt);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::data_structures::min_stack::Stack;
+use crate::data_structures::monoid::{Max, Min, Monoid};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+// Monoid with the operands swapped, so that a stack aggregates from top to bottom.
+#[derive(Debug, Clone)]
+struct Rev<M>(PhantomData<M>);
+
+impl<M: Monoid> Monoid for Rev<M> {
+    type S = M::S;
+
+    fn identity() -> M::S {
+        M::identity()
+    }
+
+    fn combine(a: &M::S, b: &M::S) -> M::S {
+        M::combine(b, a)
+    }
+}
+
+/// Queue with an O(1) amortized aggregate of all its elements for any monoid.
+/// Implemented with two aggregate stacks: elements are pushed onto the back stack and,
+/// when the front stack runs empty, moved over to it in reversed order.
+/// The aggregate of the queue is the aggregate of the front stack combined with the back one.
+#[derive(Clone)]
+pub struct AggQueue<M: Monoid> {
+    front: Stack<Rev<M>>,
+    back: Stack<M>,
+}
+
+pub type MinQueue<T> = AggQueue<Min<T>>;
+pub type MaxQueue<T> = AggQueue<Max<T>>;
+
+impl<M: Monoid> AggQueue<M> {
+    pub fn new() -> Self {
+        AggQueue {
+            front: Stack::new(),
+            back: Stack::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+
+    pub fn push_back(&mut self, val: M::S) {
+        self.back.push(val);
+    }
+
+    pub fn pop_front(&mut self) -> Option<M::S> {
+        if self.front.is_empty() {
+            while let Some(val) = self.back.pop() {
+                self.front.push(val);
+            }
+        }
+
+        self.front.pop()
+    }
+
+    pub fn front(&self) -> Option<&M::S> {
+        self.front.peek().or_else(|| self.back.iter().next())
+    }
+
+    /// Aggregate of all elements from front to back, the identity if the queue is empty.
+    pub fn aggregate(&self) -> M::S {
+        match (self.front.aggregate(), self.back.aggregate()) {
+            (Some(f), Some(b)) => M::combine(f, b),
+            (Some(f), None) => f.clone(),
+            (None, Some(b)) => b.clone(),
+            (None, None) => M::identity(),
+        }
+    }
+}
+
+impl<M: Monoid> Debug for AggQueue<M>
+where
+    M::S: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the elements from front to back, like `VecDeque`
+        f.debug_list()
+            .entries(self.front.iter().rev().chain(self.back.iter()))
+            .finish()
+    }
+}
+
+impl<M: Monoid> Default for AggQueue<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Minimum of every window of `k` consecutive elements, O(n) with a monotonic deque.
+pub fn sliding_window_min<T: Ord + Copy>(nums: &[T], k: usize) -> Vec<T> {
+    sliding_window(nums, k, |a, b| a <= b)
+}
+
+/// Maximum of every window of `k` consecutive elements, O(n) with a monotonic deque.
+pub fn sliding_window_max<T: Ord + Copy>(nums: &[T], k: usize) -> Vec<T> {
+    sliding_window(nums, k, |a, b| a >= b)
+}
+
+// `better(a, b)` tells whether `a` makes an older `b` irrelevant for all later windows.
+fn sliding_window<T, F>(nums: &[T], k: usize, better: F) -> Vec<T>
+where
+    T: Copy,
+    F: Fn(&T, &T) -> bool,
+{
+    if k == 0 || k > nums.len() {
+        return Vec::new();
+    }
+
+    let mut deque: VecDeque<usize> = VecDeque::new();
+    let mut res = Vec::with_capacity(nums.len() - k + 1);
+
+    for (i, num) in nums.iter().enumerate() {
+        while deque.back().is_some_and(|&j| better(num, &nums[j])) {
+            deque.pop_back();
+        }
+        deque.push_back(i);
+
+        if deque[0] + k <= i {
+            deque.pop_front();
+        }
+
+        if i + 1 >= k {
+            res.push(nums[deque[0]]);
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sliding_window_max, sliding_window_min, AggQueue, MinQueue};
+    use crate::data_structures::monoid::{MatrixProduct, Monoid, Sum};
+    use rand::prelude::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_min_queue() {
+        let mut queue = MinQueue::new();
+        queue.push_back(5);
+        queue.push_back(3);
+        queue.push_back(7);
+        assert_eq!(queue.aggregate(), 3);
+        assert_eq!(queue.front(), Some(&5));
+        assert_eq!(format!("{:?}", queue), "[5, 3, 7]");
+
+        assert_eq!(queue.pop_front(), Some(5));
+        assert_eq!(queue.aggregate(), 3);
+        queue.push_back(1);
+        assert_eq!(format!("{:?}", queue), "[3, 7, 1]");
+        assert_eq!(queue.aggregate(), 1);
+        assert_eq!(queue.pop_front(), Some(3));
+        assert_eq!(queue.pop_front(), Some(7));
+        assert_eq!(queue.aggregate(), 1);
+        assert_eq!(queue.pop_front(), Some(1));
+        assert_eq!(queue.pop_front(), None);
+        assert_eq!(queue.aggregate(), i32::MAX);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_agg_queue_random() {
+        type M = MatrixProduct<i64, 2>;
+        let mut rng = thread_rng();
+        let mut queue = AggQueue::<M>::new();
+        let mut sums = AggQueue::<Sum<i64>>::new();
+        let mut naive: VecDeque<[[i64; 2]; 2]> = VecDeque::new();
+
+        for _ in 0..2000 {
+            if rng.gen_bool(0.55) {
+                let val = [[rng.gen_range(-1..=1), 1], [1, rng.gen_range(-1..=1)]];
+                queue.push_back(val);
+                sums.push_back(val[0][0]);
+                naive.push_back(val);
+            } else {
+                let expected = naive.pop_front();
+                assert_eq!(queue.pop_front(), expected);
+                assert_eq!(sums.pop_front(), expected.map(|m| m[0][0]));
+            }
+
+            assert_eq!(queue.len(), naive.len());
+            assert_eq!(queue.front(), naive.front());
+            assert_eq!(
+                queue.aggregate(),
+                naive
+                    .iter()
+                    .fold(M::identity(), |acc, x| M::combine(&acc, x))
+            );
+            assert_eq!(sums.aggregate(), naive.iter().map(|m| m[0][0]).sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let nums = [1, 3, -1, -3, 5, 3, 6, 7];
+        assert_eq!(sliding_window_max(&nums, 3), vec![3, 3, 5, 5, 6, 7]);
+        assert_eq!(sliding_window_min(&nums, 3), vec![-1, -3, -3, -3, 3, 3]);
+        assert_eq!(sliding_window_min(&nums, 1), nums.to_vec());
+        assert_eq!(sliding_window_min(&nums, 9), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_sliding_window_random() {
+        let mut rng = thread_rng();
+        let nums: Vec<i32> = (0..500).map(|_| rng.gen_range(0..50)).collect();
+
+        for k in 1..20 {
+            let windows = nums.windows(k);
+            let expected_min: Vec<i32> =
+                windows.clone().map(|w| *w.iter().min().unwrap()).collect();
+            let expected_max: Vec<i32> = windows.map(|w| *w.iter().max().unwrap()).collect();
+            assert_eq!(sliding_window_min(&nums, k), expected_min);
+            assert_eq!(sliding_window_max(&nums, k), expected_max);
+        }
+    }
+}
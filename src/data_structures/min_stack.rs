@@ -1,53 +1,106 @@
+use crate::data_structures::monoid::{Max, Min, Monoid};
+
+/// Stack that keeps the aggregate of all its elements for any monoid.
+/// Every element is stored together with the aggregate of itself and everything below it,
+/// so `aggregate` is O(1). Several aggregates can be tracked at once with a tuple monoid,
+/// e.g. `Stack<(Min<T>, Max<T>, Sum<T>)>`.
 #[derive(Debug, Clone)]
-pub struct Stack<T> {
-    pub stack: Vec<T>,
-    min_stack: Vec<T>,
+pub struct Stack<M: Monoid> {
+    stack: Vec<M::S>,
+    agg_stack: Vec<M::S>,
 }
 
-impl<T> Stack<T>
-where
-    T: Ord + Copy,
-{
+pub type MinStack<T> = Stack<Min<T>>;
+pub type MaxStack<T> = Stack<Max<T>>;
+
+impl<M: Monoid> Stack<M> {
     pub fn new() -> Self {
         Stack {
             stack: Vec::new(),
-            min_stack: Vec::new(),
+            agg_stack: Vec::new(),
         }
     }
 
-    pub fn add(&mut self, num: T) {
-        self.stack.push(num);
-        let new_min = if self.min_stack.is_empty() {
-            num
-        } else {
-            num.min(self.min_stack.last().copied().unwrap())
+    pub fn push(&mut self, val: M::S) {
+        let agg = match self.agg_stack.last() {
+            Some(agg) => M::combine(agg, &val),
+            None => val.clone(),
         };
 
-        self.min_stack.push(new_min);
+        self.stack.push(val);
+        self.agg_stack.push(agg);
+    }
+
+    /// Same as `push`.
+    pub fn add(&mut self, val: M::S) {
+        self.push(val);
+    }
+
+    pub fn pop(&mut self) -> Option<M::S> {
+        self.agg_stack.pop();
+        self.stack.pop()
+    }
+
+    pub fn peek(&self) -> Option<&M::S> {
+        self.stack.last()
     }
 
-    pub fn pop(&mut self) {
-        self.stack.pop();
-        self.min_stack.pop();
+    pub fn len(&self) -> usize {
+        self.stack.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Iterates from the bottom to the top of the stack.
+    pub fn iter(&self) -> std::slice::Iter<'_, M::S> {
+        self.stack.iter()
+    }
+
+    /// Aggregate of all elements from bottom to top, `None` if the stack is empty.
+    pub fn aggregate(&self) -> Option<&M::S> {
+        self.agg_stack.last()
+    }
+}
+
+impl<T> Stack<Min<T>>
+where
+    T: Ord + Copy + num::Bounded,
+{
     pub fn get_min(&self) -> Option<&T> {
-        self.min_stack.last()
+        self.aggregate()
     }
 }
 
-impl<T> Default for Stack<T>
+impl<T> Stack<Max<T>>
 where
-    T: Ord + Copy,
+    T: Ord + Copy + num::Bounded,
 {
+    pub fn get_max(&self) -> Option<&T> {
+        self.aggregate()
+    }
+}
+
+impl<M: Monoid> Default for Stack<M> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl<'a, M: Monoid> IntoIterator for &'a Stack<M> {
+    type Item = &'a M::S;
+    type IntoIter = std::slice::Iter<'a, M::S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Stack;
+    use super::{MaxStack, Stack};
+    use crate::data_structures::monoid::{Gcd, Max, Min, Sum};
 
     #[test]
     fn test_min_stack() {
@@ -93,4 +146,41 @@ mod test {
         let min = stack.get_min().unwrap();
         assert_eq!(*min, 8);
     }
+
+    #[test]
+    fn test_max_stack() {
+        let mut stack = MaxStack::new();
+        stack.push(3);
+        stack.push(9);
+        stack.push(4);
+        assert_eq!(stack.get_max(), Some(&9));
+        assert_eq!(stack.peek(), Some(&4));
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(9));
+        assert_eq!(stack.get_max(), Some(&3));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.peek(), None);
+    }
+
+    #[test]
+    fn test_tuple_stack() {
+        let mut stack = Stack::<(Min<i32>, Max<i32>, Sum<i32>, Gcd<i32>)>::new();
+        for x in [12, 18, 30, 6, 9] {
+            stack.push((x, x, x, x));
+        }
+
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.aggregate(), Some(&(6, 30, 75, 3)));
+        stack.pop();
+        assert_eq!(stack.aggregate(), Some(&(6, 30, 66, 6)));
+
+        let values: Vec<i32> = stack.iter().map(|x| x.0).collect();
+        assert_eq!(values, vec![12, 18, 30, 6]);
+        assert_eq!((&stack).into_iter().count(), 4);
+
+        while stack.pop().is_some() {}
+        assert!(stack.is_empty());
+        assert_eq!(stack.aggregate(), None);
+    }
 }
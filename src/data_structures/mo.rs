@@ -0,0 +1,564 @@
+/// State of a window over an array for Mo's algorithm.
+/// The driver moves the window one element at a time and asks for the answer of every
+/// query once the window matches its range.
+pub trait MoSolver {
+    type Answer;
+
+    /// The element at index `i` enters the window.
+    fn add(&mut self, i: usize);
+
+    /// The element at index `i` leaves the window.
+    fn remove(&mut self, i: usize);
+
+    fn answer(&self) -> Self::Answer;
+}
+
+/// Mo's algorithm with point updates, the queries gain a time dimension.
+pub trait MoUpdateSolver: MoSolver {
+    /// Applies update `t` to the array if it is not applied, reverts it otherwise.
+    /// If the updated position is inside the window, the driver removes it before
+    /// and adds it back after the call.
+    fn toggle_update(&mut self, t: usize);
+}
+
+/// Order in which the queries are answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoOrder {
+    /// Sorted by the block of the left end, then by the right end alternating between
+    /// blocks, blocks of size n / sqrt(q).
+    Block,
+    /// Sorted along a Hilbert curve over the (l, r) plane, usually faster for many queries.
+    Hilbert,
+}
+
+// position of (x, y) along the Hilbert curve filling the 2^log x 2^log grid
+fn hilbert_order(mut x: u64, mut y: u64, log: u32) -> u64 {
+    let n = 1u64 << log;
+    let mut d = 0;
+    let mut s = n >> 1;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+
+    d
+}
+
+fn sort_queries(n: usize, ranges: &[(usize, usize)], order: MoOrder) -> Vec<usize> {
+    let mut idx: Vec<usize> = (0..ranges.len()).collect();
+    match order {
+        MoOrder::Block => {
+            let block = ((n as f64 / (ranges.len().max(1) as f64).sqrt()) as usize).max(1);
+            idx.sort_by_key(|&i| {
+                let (l, r) = ranges[i];
+                let b = l / block;
+                (
+                    b,
+                    if b.is_multiple_of(2) {
+                        r
+                    } else {
+                        usize::MAX - r
+                    },
+                )
+            });
+        }
+        MoOrder::Hilbert => {
+            let log = (usize::BITS - n.leading_zeros()).max(1);
+            idx.sort_by_cached_key(|&i| hilbert_order(ranges[i].0 as u64, ranges[i].1 as u64, log));
+        }
+    }
+
+    idx
+}
+
+// Answers the half-open `ranges` in the given order, `answer` is called with the query and
+// the window once the window matches the range of the query.
+fn solve_in_order<S, F>(
+    ranges: &[(usize, usize)],
+    order: &[usize],
+    solver: &mut S,
+    mut answer: F,
+) -> Vec<S::Answer>
+where
+    S: MoSolver,
+    F: FnMut(&mut S, usize, (usize, usize)) -> S::Answer,
+{
+    let mut res: Vec<Option<S::Answer>> = (0..ranges.len()).map(|_| None).collect();
+    let (mut cl, mut cr) = (0, 0);
+
+    for &q in order {
+        let (l, r) = ranges[q];
+        // grow the window first, so it never has a negative length
+        while cl > l {
+            cl -= 1;
+            solver.add(cl);
+        }
+        while cr < r {
+            solver.add(cr);
+            cr += 1;
+        }
+        while cl < l {
+            solver.remove(cl);
+            cl += 1;
+        }
+        while cr > r {
+            cr -= 1;
+            solver.remove(cr);
+        }
+
+        res[q] = Some(answer(solver, q, (cl, cr)));
+    }
+
+    res.into_iter().map(|ans| ans.unwrap()).collect()
+}
+
+/// Mo's algorithm
+/// Answers offline queries on the inclusive ranges [l, r] of an array of length `n` by
+/// moving a window over the array in an order that keeps the total movement at
+/// O(n sqrt q), so every query costs amortized O(n / sqrt q) calls of `add` and `remove`.
+pub fn mo<S: MoSolver>(
+    n: usize,
+    queries: &[(usize, usize)],
+    order: MoOrder,
+    solver: &mut S,
+) -> Vec<S::Answer> {
+    let ranges: Vec<(usize, usize)> = queries
+        .iter()
+        .map(|&(l, r)| {
+            assert!(l <= r && r < n, "query range out of bounds");
+            (l, r + 1)
+        })
+        .collect();
+
+    let order = sort_queries(n, &ranges, order);
+    solve_in_order(&ranges, &order, solver, |solver, _, _| solver.answer())
+}
+
+/// Mo's algorithm with updates
+/// A query (l, r, t) asks about the inclusive range [l, r] after the first `t` updates,
+/// `update_positions[t]` is the index changed by update t. The queries are grouped by the
+/// blocks of l and r of size n^(2/3), the groups are visited in `order` and the queries of
+/// a group by time, O(n^(5/3)) moves in total.
+pub fn mo_with_updates<S: MoUpdateSolver>(
+    n: usize,
+    queries: &[(usize, usize, usize)],
+    update_positions: &[usize],
+    order: MoOrder,
+    solver: &mut S,
+) -> Vec<S::Answer> {
+    let ranges: Vec<(usize, usize)> = queries
+        .iter()
+        .map(|&(l, r, t)| {
+            assert!(l <= r && r < n, "query range out of bounds");
+            assert!(t <= update_positions.len(), "query time out of bounds");
+            (l, r + 1)
+        })
+        .collect();
+
+    let block = ((n as f64).powf(2.0 / 3.0) as usize).max(1);
+    let blocks: Vec<(usize, usize)> = queries
+        .iter()
+        .map(|&(l, r, _)| (l / block, r / block))
+        .collect();
+    let mut idx: Vec<usize> = (0..queries.len()).collect();
+    match order {
+        MoOrder::Block => idx.sort_by_key(|&i| (blocks[i], queries[i].2)),
+        MoOrder::Hilbert => {
+            let cells = n / block + 1;
+            let log = (usize::BITS - cells.leading_zeros()).max(1);
+            idx.sort_by_cached_key(|&i| {
+                let (bl, br) = blocks[i];
+                (hilbert_order(bl as u64, br as u64, log), queries[i].2)
+            });
+        }
+    }
+
+    // moves the time of the window to the time of the query before answering it
+    let mut ct = 0;
+    solve_in_order(&ranges, &idx, solver, |solver, q, (cl, cr)| {
+        let t = queries[q].2;
+        while ct != t {
+            let u = if ct < t { ct } else { ct - 1 };
+            let inside = cl <= update_positions[u] && update_positions[u] < cr;
+            if inside {
+                solver.remove(update_positions[u]);
+            }
+            solver.toggle_update(u);
+            if inside {
+                solver.add(update_positions[u]);
+            }
+
+            if ct < t {
+                ct += 1;
+            } else {
+                ct -= 1;
+            }
+        }
+
+        solver.answer()
+    })
+}
+
+// Runs a solver over vertices on the positions of an Euler tour where every vertex appears
+// on entry and exit. A vertex is in the window iff it appears there exactly once.
+struct TreeAdaptor<'a, S: MoSolver> {
+    solver: &'a mut S,
+    tour: Vec<usize>,
+    active: Vec<bool>,
+}
+
+impl<S: MoSolver> TreeAdaptor<'_, S> {
+    fn toggle(&mut self, v: usize) {
+        if self.active[v] {
+            self.solver.remove(v);
+        } else {
+            self.solver.add(v);
+        }
+        self.active[v] ^= true;
+    }
+}
+
+impl<S: MoSolver> MoSolver for TreeAdaptor<'_, S> {
+    type Answer = S::Answer;
+
+    fn add(&mut self, i: usize) {
+        self.toggle(self.tour[i]);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.toggle(self.tour[i]);
+    }
+
+    fn answer(&self) -> S::Answer {
+        self.solver.answer()
+    }
+}
+
+/// Mo's algorithm on the paths of a tree given as an adjacency list.
+/// Every vertex appears twice in the Euler tour, on entry and on exit, so the vertices
+/// appearing once in the tour between u and v are exactly the path without the lowest
+/// common ancestor, which is added separately while answering.
+/// The solver receives vertex ids in `add` and `remove`.
+pub fn mo_on_tree<S: MoSolver>(
+    al: &[Vec<usize>],
+    root: usize,
+    queries: &[(usize, usize)],
+    order: MoOrder,
+    solver: &mut S,
+) -> Vec<S::Answer> {
+    let n = al.len();
+    assert!(root < n, "invalid root");
+
+    let mut tin = vec![0; n];
+    let mut tout = vec![0; n];
+    let mut tour = Vec::with_capacity(2 * n);
+    let log = (usize::BITS - n.leading_zeros()).max(1) as usize;
+    let mut up = vec![vec![root; n]; log];
+
+    // iterative dfs, the flag marks the exit of a vertex
+    let mut visited = vec![false; n];
+    let mut stack = vec![(root, false)];
+    while let Some((u, exit)) = stack.pop() {
+        if exit {
+            tout[u] = tour.len();
+            tour.push(u);
+            continue;
+        }
+
+        visited[u] = true;
+        tin[u] = tour.len();
+        tour.push(u);
+        stack.push((u, true));
+        for &v in al[u].iter().rev() {
+            if !visited[v] {
+                up[0][v] = u;
+                stack.push((v, false));
+            }
+        }
+    }
+    assert!(tour.len() == 2 * n, "the graph has to be a connected tree");
+
+    for k in 1..log {
+        for v in 0..n {
+            up[k][v] = up[k - 1][up[k - 1][v]];
+        }
+    }
+
+    let is_ancestor = |u: usize, v: usize| tin[u] <= tin[v] && tout[v] <= tout[u];
+    let lca = |u: usize, v: usize| {
+        if is_ancestor(u, v) {
+            return u;
+        }
+
+        let mut u = u;
+        for k in (0..log).rev() {
+            if !is_ancestor(up[k][u], v) {
+                u = up[k][u];
+            }
+        }
+        up[0][u]
+    };
+
+    // half-open ranges of the tour and the lca to add when it is not an endpoint
+    let mut ranges = Vec::with_capacity(queries.len());
+    let mut extra = Vec::with_capacity(queries.len());
+    for &(u, v) in queries {
+        assert!(u < n && v < n, "invalid node index");
+        let (u, v) = if tin[u] <= tin[v] { (u, v) } else { (v, u) };
+        let w = lca(u, v);
+        if w == u {
+            ranges.push((tin[u], tin[v] + 1));
+            extra.push(None);
+        } else {
+            ranges.push((tout[u], tin[v] + 1));
+            extra.push(Some(w));
+        }
+    }
+
+    let order = sort_queries(2 * n, &ranges, order);
+    let mut adaptor = TreeAdaptor {
+        solver,
+        tour,
+        active: vec![false; n],
+    };
+
+    solve_in_order(&ranges, &order, &mut adaptor, |adaptor, q, _| {
+        match extra[q] {
+            Some(w) => {
+                adaptor.solver.add(w);
+                let ans = adaptor.solver.answer();
+                adaptor.solver.remove(w);
+                ans
+            }
+            None => adaptor.solver.answer(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mo, mo_on_tree, mo_with_updates, MoOrder, MoSolver, MoUpdateSolver};
+    use rand::prelude::*;
+    use std::collections::HashSet;
+
+    struct Distinct {
+        nums: Vec<usize>,
+        count: Vec<usize>,
+        distinct: usize,
+        updates: Vec<(usize, usize)>,
+    }
+
+    impl Distinct {
+        fn new(nums: Vec<usize>, updates: Vec<(usize, usize)>) -> Self {
+            Distinct {
+                nums,
+                count: vec![0; 100],
+                distinct: 0,
+                updates,
+            }
+        }
+    }
+
+    impl MoSolver for Distinct {
+        type Answer = usize;
+
+        fn add(&mut self, i: usize) {
+            self.count[self.nums[i]] += 1;
+            if self.count[self.nums[i]] == 1 {
+                self.distinct += 1;
+            }
+        }
+
+        fn remove(&mut self, i: usize) {
+            self.count[self.nums[i]] -= 1;
+            if self.count[self.nums[i]] == 0 {
+                self.distinct -= 1;
+            }
+        }
+
+        fn answer(&self) -> usize {
+            self.distinct
+        }
+    }
+
+    impl MoUpdateSolver for Distinct {
+        fn toggle_update(&mut self, t: usize) {
+            let (pos, val) = &mut self.updates[t];
+            std::mem::swap(&mut self.nums[*pos], val);
+        }
+    }
+
+    // frequency of the most frequent value, the counts of counts let `remove` lower it in O(1)
+    struct Mode {
+        nums: Vec<usize>,
+        count: Vec<usize>,
+        count_of_count: Vec<usize>,
+        best: usize,
+    }
+
+    impl MoSolver for Mode {
+        type Answer = usize;
+
+        fn add(&mut self, i: usize) {
+            let c = &mut self.count[self.nums[i]];
+            self.count_of_count[*c] -= 1;
+            *c += 1;
+            self.count_of_count[*c] += 1;
+            self.best = self.best.max(*c);
+        }
+
+        fn remove(&mut self, i: usize) {
+            let c = &mut self.count[self.nums[i]];
+            self.count_of_count[*c] -= 1;
+            if *c == self.best && self.count_of_count[*c] == 0 {
+                self.best -= 1;
+            }
+            *c -= 1;
+            self.count_of_count[*c] += 1;
+        }
+
+        fn answer(&self) -> usize {
+            self.best
+        }
+    }
+
+    fn random_ranges(rng: &mut ThreadRng, n: usize, q: usize) -> Vec<(usize, usize)> {
+        (0..q)
+            .map(|_| {
+                let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                (a.min(b), a.max(b))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mo_distinct() {
+        let mut rng = thread_rng();
+        let n = 300;
+        let nums: Vec<usize> = (0..n).map(|_| rng.gen_range(0..20)).collect();
+        let queries = random_ranges(&mut rng, n, 500);
+        let expected: Vec<usize> = queries
+            .iter()
+            .map(|&(l, r)| nums[l..=r].iter().collect::<HashSet<_>>().len())
+            .collect();
+
+        for order in [MoOrder::Block, MoOrder::Hilbert] {
+            let mut solver = Distinct::new(nums.clone(), Vec::new());
+            assert_eq!(mo(n, &queries, order, &mut solver), expected);
+        }
+    }
+
+    #[test]
+    fn test_mo_mode() {
+        let mut rng = thread_rng();
+        let n = 300;
+        let nums: Vec<usize> = (0..n).map(|_| rng.gen_range(0..10)).collect();
+        let queries = random_ranges(&mut rng, n, 500);
+        let mut solver = Mode {
+            nums: nums.clone(),
+            count: vec![0; 10],
+            count_of_count: {
+                let mut c = vec![0; n + 1];
+                c[0] = 10;
+                c
+            },
+            best: 0,
+        };
+
+        let res = mo(n, &queries, MoOrder::Hilbert, &mut solver);
+        for (&(l, r), ans) in queries.iter().zip(res) {
+            let mut count = [0; 10];
+            for &x in &nums[l..=r] {
+                count[x] += 1;
+            }
+            assert_eq!(ans, *count.iter().max().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_mo_with_updates() {
+        let mut rng = thread_rng();
+        let n = 200;
+        let nums: Vec<usize> = (0..n).map(|_| rng.gen_range(0..20)).collect();
+        let updates: Vec<(usize, usize)> = (0..300)
+            .map(|_| (rng.gen_range(0..n), rng.gen_range(0..20)))
+            .collect();
+        let queries: Vec<(usize, usize, usize)> = random_ranges(&mut rng, n, 400)
+            .into_iter()
+            .map(|(l, r)| (l, r, rng.gen_range(0..=updates.len())))
+            .collect();
+
+        let positions: Vec<usize> = updates.iter().map(|&(pos, _)| pos).collect();
+        for order in [MoOrder::Block, MoOrder::Hilbert] {
+            let mut solver = Distinct::new(nums.clone(), updates.clone());
+            let res = mo_with_updates(n, &queries, &positions, order, &mut solver);
+
+            for (&(l, r, t), ans) in queries.iter().zip(res) {
+                let mut arr = nums.clone();
+                for &(pos, val) in &updates[..t] {
+                    arr[pos] = val;
+                }
+                assert_eq!(ans, arr[l..=r].iter().collect::<HashSet<_>>().len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_mo_on_tree() {
+        let mut rng = thread_rng();
+        let n = 150;
+        let colors: Vec<usize> = (0..n).map(|_| rng.gen_range(0..15)).collect();
+        let mut al = vec![Vec::new(); n];
+        let mut parent = vec![usize::MAX; n];
+        for v in 1..n {
+            let u = rng.gen_range(0..v);
+            al[u].push(v);
+            al[v].push(u);
+            parent[v] = u;
+        }
+
+        let queries: Vec<(usize, usize)> = (0..300)
+            .map(|_| (rng.gen_range(0..n), rng.gen_range(0..n)))
+            .collect();
+
+        for order in [MoOrder::Block, MoOrder::Hilbert] {
+            let mut solver = Distinct::new(colors.clone(), Vec::new());
+            let res = mo_on_tree(&al, 0, &queries, order, &mut solver);
+
+            for (&(u, v), ans) in queries.iter().zip(res) {
+                // path through the root of the generated tree, vertex 0
+                let ancestors = |mut x: usize| {
+                    let mut path = vec![x];
+                    while parent[x] != usize::MAX {
+                        x = parent[x];
+                        path.push(x);
+                    }
+                    path
+                };
+                let (pu, pv) = (ancestors(u), ancestors(v));
+                let common: HashSet<usize> = pu.iter().copied().collect();
+                let lca = *pv.iter().find(|x| common.contains(x)).unwrap();
+
+                let mut seen = HashSet::new();
+                for path in [&pu, &pv] {
+                    for &x in path.iter() {
+                        seen.insert(colors[x]);
+                        if x == lca {
+                            break;
+                        }
+                    }
+                }
+                assert_eq!(ans, seen.len());
+            }
+        }
+    }
+}
@@ -1,5 +1,22 @@
+pub mod convex_hull_trick;
+pub mod disjoint_sparse_table;
+pub mod dynamic_segment_tree;
 pub mod fenwick_tree;
+pub mod lazy_segment_tree;
+pub mod li_chao_tree;
+pub mod merge_sort_tree;
+pub mod min_queue;
 pub mod min_stack;
+pub mod mo;
+pub mod monoid;
+pub mod persistent_array;
+pub mod persistent_segment_tree;
 pub mod segment_tree;
+pub mod segment_tree_beats;
 pub mod sparse_table;
+pub mod treap;
 pub mod union_find;
+pub mod wavelet_matrix;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
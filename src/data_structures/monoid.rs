@@ -0,0 +1,369 @@
+use num::{Bounded, Integer, One, Zero};
+use std::marker::PhantomData;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Not, Sub};
+
+/// Monoid
+/// A set with an associative binary operation and an identity element.
+/// Data structures that aggregate ranges (segment trees, sparse tables, ...) are generic
+/// over this trait, so the operation is resolved at compile time instead of through a closure.
+pub trait Monoid {
+    type S: Clone;
+
+    fn identity() -> Self::S;
+
+    fn combine(a: &Self::S, b: &Self::S) -> Self::S;
+}
+
+/// Monoid whose operation is commutative, so elements can be combined in any order.
+pub trait Commutative: Monoid {}
+
+/// Monoid where every element has an inverse (a group), which allows aggregates
+/// of ranges to be computed as differences of prefix aggregates.
+pub trait Invertible: Monoid {
+    fn inverse(a: &Self::S) -> Self::S;
+}
+
+/// Monoid whose operation is idempotent (x * x = x), so overlapping ranges can be combined
+/// without counting an element twice.
+pub trait Idempotent: Monoid {}
+
+/// Combines `x` with itself `n` times using binary exponentiation, O(log n).
+pub fn pow<M: Monoid>(x: &M::S, mut n: u64) -> M::S {
+    let mut res = M::identity();
+    let mut base = x.clone();
+
+    while n > 0 {
+        if n & 1 == 1 {
+            res = M::combine(&res, &base);
+        }
+
+        n >>= 1;
+        if n > 0 {
+            base = M::combine(&base, &base);
+        }
+    }
+
+    res
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sum<T>(PhantomData<T>);
+
+impl<T> Monoid for Sum<T>
+where
+    T: Copy + Zero + Add<Output = T>,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a + *b
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Min<T>(PhantomData<T>);
+
+impl<T> Monoid for Min<T>
+where
+    T: Copy + Ord + Bounded,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::max_value()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a.min(b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Max<T>(PhantomData<T>);
+
+impl<T> Monoid for Max<T>
+where
+    T: Copy + Ord + Bounded,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::min_value()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a.max(b)
+    }
+}
+
+/// Greatest common divisor, the identity is 0 since gcd(0, x) = x.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gcd<T>(PhantomData<T>);
+
+impl<T> Monoid for Gcd<T>
+where
+    T: Copy + Integer,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        a.gcd(b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xor<T>(PhantomData<T>);
+
+impl<T> Monoid for Xor<T>
+where
+    T: Copy + Zero + BitXor<Output = T>,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a ^ *b
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Or<T>(PhantomData<T>);
+
+impl<T> Monoid for Or<T>
+where
+    T: Copy + Zero + BitOr<Output = T>,
+{
+    type S = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a | *b
+    }
+}
+
+/// Bitwise and, the identity has all bits set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct And<T>(PhantomData<T>);
+
+impl<T> Monoid for And<T>
+where
+    T: Copy + Zero + Not<Output = T> + BitAnd<Output = T>,
+{
+    type S = T;
+
+    fn identity() -> T {
+        !T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a & *b
+    }
+}
+
+/// Product modulo `P`.
+/// Not `Invertible`: zero has no inverse, so a range containing it cannot be divided out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProductMod<const P: u64>;
+
+impl<const P: u64> Monoid for ProductMod<P> {
+    type S = u64;
+
+    fn identity() -> u64 {
+        1 % P
+    }
+
+    fn combine(a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % P as u128) as u64
+    }
+}
+
+/// Minimum together with its position, ties are broken by the smaller index.
+/// The identity is `(T::max_value(), usize::MAX)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinWithIndex<T>(PhantomData<T>);
+
+impl<T> Monoid for MinWithIndex<T>
+where
+    T: Copy + Ord + Bounded,
+{
+    type S = (T, usize);
+
+    fn identity() -> (T, usize) {
+        (T::max_value(), usize::MAX)
+    }
+
+    fn combine(a: &(T, usize), b: &(T, usize)) -> (T, usize) {
+        *a.min(b)
+    }
+}
+
+impl<T> Commutative for Sum<T> where T: Copy + Zero + Add<Output = T> {}
+impl<T> Commutative for Min<T> where T: Copy + Ord + Bounded {}
+impl<T> Commutative for Max<T> where T: Copy + Ord + Bounded {}
+impl<T> Commutative for Gcd<T> where T: Copy + Integer {}
+impl<T> Commutative for Xor<T> where T: Copy + Zero + BitXor<Output = T> {}
+impl<T> Commutative for Or<T> where T: Copy + Zero + BitOr<Output = T> {}
+impl<T> Commutative for And<T> where T: Copy + Zero + Not<Output = T> + BitAnd<Output = T> {}
+impl<T> Commutative for MinWithIndex<T> where T: Copy + Ord + Bounded {}
+impl<const P: u64> Commutative for ProductMod<P> {}
+
+impl<T> Idempotent for Min<T> where T: Copy + Ord + Bounded {}
+impl<T> Idempotent for Max<T> where T: Copy + Ord + Bounded {}
+impl<T> Idempotent for Gcd<T> where T: Copy + Integer {}
+impl<T> Idempotent for Or<T> where T: Copy + Zero + BitOr<Output = T> {}
+impl<T> Idempotent for And<T> where T: Copy + Zero + Not<Output = T> + BitAnd<Output = T> {}
+impl<T> Idempotent for MinWithIndex<T> where T: Copy + Ord + Bounded {}
+
+impl<T> Invertible for Sum<T>
+where
+    T: Copy + Zero + Add<Output = T> + Sub<Output = T>,
+{
+    fn inverse(a: &T) -> T {
+        T::zero() - *a
+    }
+}
+
+impl<T> Invertible for Xor<T>
+where
+    T: Copy + Zero + BitXor<Output = T>,
+{
+    fn inverse(a: &T) -> T {
+        *a
+    }
+}
+
+/// Several monoids aggregated side by side, e.g. `(Min<T>, Max<T>, Sum<T>)` keeps the
+/// minimum, maximum and sum of the same values at once.
+macro_rules! impl_tuple_monoid {
+    ($($m:ident $idx:tt),+) => {
+        impl<$($m: Monoid),+> Monoid for ($($m,)+) {
+            type S = ($($m::S,)+);
+
+            fn identity() -> Self::S {
+                ($($m::identity(),)+)
+            }
+
+            fn combine(a: &Self::S, b: &Self::S) -> Self::S {
+                ($($m::combine(&a.$idx, &b.$idx),)+)
+            }
+        }
+    };
+}
+
+impl_tuple_monoid!(A 0, B 1);
+impl_tuple_monoid!(A 0, B 1, C 2);
+impl_tuple_monoid!(A 0, B 1, C 2, D 3);
+
+/// Product of N x N matrices, the identity is the identity matrix.
+/// Not commutative, so the order of the aggregated range matters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatrixProduct<T, const N: usize>(PhantomData<T>);
+
+impl<T, const N: usize> Monoid for MatrixProduct<T, N>
+where
+    T: Copy + Zero + One + Add<Output = T> + Mul<Output = T>,
+{
+    type S = [[T; N]; N];
+
+    fn identity() -> [[T; N]; N] {
+        let mut res = [[T::zero(); N]; N];
+        for (i, row) in res.iter_mut().enumerate() {
+            row[i] = T::one();
+        }
+
+        res
+    }
+
+    fn combine(a: &[[T; N]; N], b: &[[T; N]; N]) -> [[T; N]; N] {
+        let mut res = [[T::zero(); N]; N];
+        for i in 0..N {
+            for k in 0..N {
+                for j in 0..N {
+                    res[i][j] = res[i][j] + a[i][k] * b[k][j];
+                }
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        pow, And, Gcd, Invertible, MatrixProduct, Max, Min, MinWithIndex, Monoid, Or, Sum, Xor,
+    };
+
+    fn fold<M: Monoid>(nums: &[M::S]) -> M::S {
+        nums.iter()
+            .fold(M::identity(), |acc, x| M::combine(&acc, x))
+    }
+
+    #[test]
+    fn test_basic_monoids() {
+        let nums = [12, 18, 30, 6];
+        assert_eq!(fold::<Sum<i32>>(&nums), 66);
+        assert_eq!(fold::<Min<i32>>(&nums), 6);
+        assert_eq!(fold::<Max<i32>>(&nums), 30);
+        assert_eq!(fold::<Gcd<i32>>(&nums), 6);
+        assert_eq!(fold::<Xor<i32>>(&nums), 6);
+        assert_eq!(fold::<Min<i32>>(&[]), i32::MAX);
+        assert_eq!(pow::<Sum<i64>>(&3, 1_000_000_000_000), 3_000_000_000_000);
+        assert_eq!(pow::<Sum<i64>>(&3, 0), 0);
+    }
+
+    #[test]
+    fn test_bitwise_monoids() {
+        let nums = [0b1100u8, 0b1010, 0b1110];
+        assert_eq!(fold::<Or<u8>>(&nums), 0b1110);
+        assert_eq!(fold::<And<u8>>(&nums), 0b1000);
+        assert_eq!(fold::<And<u8>>(&[]), u8::MAX);
+    }
+
+    #[test]
+    fn test_inverses() {
+        assert_eq!(Sum::<i32>::combine(&5, &Sum::<i32>::inverse(&5)), 0);
+        assert_eq!(Xor::<u32>::combine(&5, &Xor::<u32>::inverse(&5)), 0);
+    }
+
+    #[test]
+    fn test_tuple_monoid() {
+        type M = (Min<i32>, Max<i32>, Sum<i32>, Gcd<i32>);
+        let nums: Vec<_> = [12, 18, 30, 6].iter().map(|&x| (x, x, x, x)).collect();
+        assert_eq!(fold::<M>(&nums), (6, 30, 66, 6));
+        assert_eq!(fold::<(Sum<i32>, Xor<i32>)>(&[]), (0, 0));
+    }
+
+    #[test]
+    fn test_min_with_index() {
+        let nums = [(5, 0), (3, 1), (4, 2), (3, 3)];
+        assert_eq!(fold::<MinWithIndex<i32>>(&nums), (3, 1));
+    }
+
+    #[test]
+    fn test_matrix_product() {
+        // Fibonacci numbers through powers of [[1, 1], [1, 0]]
+        let fib = [[1u64, 1], [1, 0]];
+        let res = fold::<MatrixProduct<u64, 2>>(&[fib; 10]);
+        assert_eq!(res, [[89, 55], [55, 34]]);
+        assert_eq!(pow::<MatrixProduct<u64, 2>>(&fib, 10), res);
+        assert_eq!(
+            MatrixProduct::<u64, 2>::combine(&MatrixProduct::<u64, 2>::identity(), &fib),
+            fib
+        );
+    }
+}
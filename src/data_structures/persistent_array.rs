@@ -0,0 +1,111 @@
+use crate::data_structures::monoid::Monoid;
+use crate::data_structures::persistent_segment_tree::{PersistentSegmentTree, Root};
+use std::marker::PhantomData;
+
+/// Handle of a version of a `PersistentArray`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Version(Root);
+
+// Values are only kept in the leaves, inner nodes hold `None`. This does not aggregate
+// anything, so the tree is only used through `get` and `set`.
+#[derive(Debug, Clone)]
+struct Leaves<T>(PhantomData<T>);
+
+impl<T: Clone> Monoid for Leaves<T> {
+    type S = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn combine(_: &Option<T>, _: &Option<T>) -> Option<T> {
+        None
+    }
+}
+
+/// Persistent Array
+/// Fully persistent array on top of `PersistentSegmentTree`: `set` copies the O(log n)
+/// nodes on the path to the changed leaf and returns a new version, every version stays
+/// readable and can be updated again, so the versions form a tree.
+/// O(log n) time and memory per `set`, O(log n) time per `get`.
+#[derive(Debug, Clone)]
+pub struct PersistentArray<T: Clone> {
+    tree: PersistentSegmentTree<Leaves<T>>,
+}
+
+impl<T: Clone> PersistentArray<T> {
+    pub fn new(values: &[T]) -> Self {
+        let leaves: Vec<Option<T>> = values.iter().cloned().map(Some).collect();
+
+        PersistentArray {
+            tree: PersistentSegmentTree::new(&leaves),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Version holding the initial values.
+    pub fn base(&self) -> Version {
+        Version(self.tree.base())
+    }
+
+    pub fn get(&self, version: Version, i: usize) -> &T {
+        assert!(i < self.len(), "index out of range");
+        self.tree.get(version.0, i).as_ref().unwrap()
+    }
+
+    /// Sets `a[i] = val` in the given version and returns the new version.
+    pub fn set(&mut self, version: Version, i: usize, val: T) -> Version {
+        assert!(i < self.len(), "index out of range");
+        Version(self.tree.set(version.0, i, Some(val)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentArray;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_persistent_array() {
+        let mut rng = thread_rng();
+        let n = 50;
+        let initial: Vec<u32> = (0..n).map(|_| rng.gen()).collect();
+        let mut array = PersistentArray::new(&initial);
+        let mut versions = vec![(array.base(), initial)];
+
+        for _ in 0..2000 {
+            let (version, mut values) = versions.choose(&mut rng).unwrap().clone();
+            let i = rng.gen_range(0..n);
+
+            if rng.gen_bool(0.5) {
+                let val = rng.gen();
+                values[i] = val;
+                versions.push((array.set(version, i, val), values));
+            } else {
+                assert_eq!(*array.get(version, i), values[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_persistent_array_empty() {
+        let array = PersistentArray::<i32>::new(&[]);
+        assert!(array.is_empty());
+        assert_eq!(array.len(), 0);
+        assert!(!PersistentArray::new(&[1]).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_persistent_array_out_of_range() {
+        let array = PersistentArray::new(&[1, 2, 3]);
+        array.get(array.base(), 3);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::data_structures::monoid::{Monoid, Sum};
+use std::cmp::{max, min};
+
+/// Handle of a version of a `PersistentSegmentTree`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Root(usize);
+
+#[derive(Debug, Clone)]
+struct Node<S> {
+    left: usize,
+    right: usize,
+    val: S,
+}
+
+/// Persistent Segment Tree
+/// Every point update copies the O(log n) nodes on the path to the updated leaf and returns
+/// a new root, all other nodes are shared with the previous version.
+/// Nodes live in a single pool, so the memory usage is O(n + q log n) for q updates.
+#[derive(Debug, Clone)]
+pub struct PersistentSegmentTree<M: Monoid> {
+    n: usize,
+    nodes: Vec<Node<M::S>>,
+    base: Root,
+}
+
+impl<M: Monoid> PersistentSegmentTree<M> {
+    pub fn new(nums: &[M::S]) -> Self {
+        let n = nums.len();
+        // node 0 is an empty sentinel used as the root of an empty tree
+        let mut segment_tree = PersistentSegmentTree {
+            n,
+            nodes: vec![Node {
+                left: 0,
+                right: 0,
+                val: M::identity(),
+            }],
+            base: Root(0),
+        };
+
+        if n > 0 {
+            segment_tree.base = Root(segment_tree.build_internal(0, n - 1, nums));
+        }
+
+        segment_tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Version built from the initial values.
+    pub fn base(&self) -> Root {
+        self.base
+    }
+
+    /// Number of allocated nodes across all versions.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Sets `a[i] = val` in the given version and returns the new version.
+    /// The given version stays unchanged.
+    pub fn set(&mut self, root: Root, i: usize, val: M::S) -> Root {
+        Root(self.set_internal(root.0, 0, self.n - 1, i, val))
+    }
+
+    pub fn get(&self, root: Root, i: usize) -> &M::S {
+        let mut p = root.0;
+        let (mut left, mut right) = (0, self.n - 1);
+
+        while left != right {
+            let m = (right - left) / 2 + left;
+            if i <= m {
+                p = self.nodes[p].left;
+                right = m;
+            } else {
+                p = self.nodes[p].right;
+                left = m + 1;
+            }
+        }
+
+        &self.nodes[p].val
+    }
+
+    /// Aggregate of the values in the inclusive range [i, j] of the given version.
+    pub fn query(&self, root: Root, i: usize, j: usize) -> M::S {
+        if self.n == 0 {
+            return M::identity();
+        }
+
+        self.query_internal(root.0, 0, self.n - 1, i, min(j, self.n - 1))
+    }
+
+    fn push(&mut self, left: usize, right: usize, val: M::S) -> usize {
+        self.nodes.push(Node { left, right, val });
+        self.nodes.len() - 1
+    }
+
+    fn pull(&mut self, left: usize, right: usize) -> usize {
+        let val = M::combine(&self.nodes[left].val, &self.nodes[right].val);
+        self.push(left, right, val)
+    }
+
+    fn build_internal(&mut self, left: usize, right: usize, nums: &[M::S]) -> usize {
+        if left == right {
+            return self.push(0, 0, nums[left].clone());
+        }
+
+        let m = (right - left) / 2 + left;
+        let l = self.build_internal(left, m, nums);
+        let r = self.build_internal(m + 1, right, nums);
+        self.pull(l, r)
+    }
+
+    fn set_internal(&mut self, p: usize, left: usize, right: usize, i: usize, val: M::S) -> usize {
+        if left == right {
+            return self.push(0, 0, val);
+        }
+
+        let m = (right - left) / 2 + left;
+        let (mut l, mut r) = (self.nodes[p].left, self.nodes[p].right);
+        if i <= m {
+            l = self.set_internal(l, left, m, i, val);
+        } else {
+            r = self.set_internal(r, m + 1, right, i, val);
+        }
+        self.pull(l, r)
+    }
+
+    fn query_internal(&self, p: usize, left: usize, right: usize, i: usize, j: usize) -> M::S {
+        if i > j {
+            return M::identity();
+        }
+
+        if left >= i && right <= j {
+            return self.nodes[p].val.clone();
+        }
+
+        let m = (right - left) / 2 + left;
+        let res_left = self.query_internal(self.nodes[p].left, left, m, i, min(m, j));
+        let res_right = self.query_internal(self.nodes[p].right, m + 1, right, max(i, m + 1), j);
+        M::combine(&res_left, &res_right)
+    }
+}
+
+/// Range order statistics on a static array.
+/// Version `i` of a persistent segment tree over the compressed values counts the
+/// occurrences of every value among the first `i` elements, so the counts of any
+/// subarray are the difference of two versions.
+#[derive(Debug, Clone)]
+pub struct KthSmallest<T: Ord + Clone> {
+    values: Vec<T>,
+    tree: PersistentSegmentTree<Sum<usize>>,
+    roots: Vec<Root>,
+}
+
+impl<T: Ord + Clone> KthSmallest<T> {
+    pub fn new(nums: &[T]) -> Self {
+        let mut values = nums.to_vec();
+        values.sort();
+        values.dedup();
+
+        let mut tree = PersistentSegmentTree::new(&vec![0; values.len()]);
+        let mut roots = vec![tree.base()];
+
+        for num in nums {
+            let idx = values.binary_search(num).unwrap();
+            let root = *roots.last().unwrap();
+            let count = *tree.get(root, idx);
+            roots.push(tree.set(root, idx, count + 1));
+        }
+
+        KthSmallest {
+            values,
+            tree,
+            roots,
+        }
+    }
+
+    /// k-th (0-based) smallest value in the inclusive range [l, r].
+    pub fn kth_smallest(&self, l: usize, r: usize, k: usize) -> Option<&T> {
+        if l > r || k > r - l {
+            return None;
+        }
+
+        let nodes = &self.tree.nodes;
+        let (mut a, mut b) = (self.roots[l].0, self.roots[r + 1].0);
+        let (mut left, mut right) = (0, self.values.len() - 1);
+        let mut k = k;
+
+        while left != right {
+            let m = (right - left) / 2 + left;
+            let on_left = nodes[nodes[b].left].val - nodes[nodes[a].left].val;
+            if k < on_left {
+                a = nodes[a].left;
+                b = nodes[b].left;
+                right = m;
+            } else {
+                k -= on_left;
+                a = nodes[a].right;
+                b = nodes[b].right;
+                left = m + 1;
+            }
+        }
+
+        Some(&self.values[left])
+    }
+
+    /// Number of values `<= x` in the inclusive range [l, r].
+    pub fn count_le(&self, l: usize, r: usize, x: &T) -> usize {
+        let bound = self.values.partition_point(|v| v <= x);
+        if l > r || bound == 0 {
+            return 0;
+        }
+
+        self.tree.query(self.roots[r + 1], 0, bound - 1)
+            - self.tree.query(self.roots[l], 0, bound - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KthSmallest, PersistentSegmentTree};
+    use crate::data_structures::monoid::{Min, Sum};
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    #[test]
+    fn test_persistent_segment_tree_versions() {
+        let mut st = PersistentSegmentTree::<Sum<i32>>::new(&[1, 2, 3, 4]);
+        let v0 = st.base();
+        let v1 = st.set(v0, 0, 10);
+        let v2 = st.set(v1, 3, 0);
+        let v3 = st.set(v0, 2, 7);
+
+        assert_eq!(st.query(v0, 0, 3), 10);
+        assert_eq!(st.query(v1, 0, 3), 19);
+        assert_eq!(st.query(v2, 0, 3), 15);
+        assert_eq!(st.query(v3, 0, 3), 14);
+        assert_eq!(st.query(v3, 0, 1), 3);
+        assert_eq!(*st.get(v2, 0), 10);
+        assert_eq!(*st.get(v3, 0), 1);
+    }
+
+    #[test]
+    fn test_persistent_segment_tree_random() {
+        let n = 100;
+        let mut rng = thread_rng();
+        let nums: Vec<i32> = (0..n).map(|_| rng.gen_range(0..1000)).collect();
+        let mut st = PersistentSegmentTree::<Min<i32>>::new(&nums);
+        let mut versions = vec![(st.base(), nums)];
+
+        for _ in 0..2000 {
+            let (root, arr) = versions.choose(&mut rng).unwrap().clone();
+            if rng.gen_bool(0.5) {
+                let i = rng.gen_range(0..n);
+                let val = rng.gen_range(0..1000);
+                let mut arr = arr;
+                arr[i] = val;
+                versions.push((st.set(root, i, val), arr));
+            } else {
+                let v1 = rng.gen_range(0..n);
+                let v2 = rng.gen_range(0..n);
+                let (l, r) = (min(v1, v2), max(v1, v2));
+                assert_eq!(st.query(root, l, r), *arr[l..=r].iter().min().unwrap());
+            }
+        }
+
+        // 2n - 1 nodes for the build, the sentinel and O(log n) per update
+        assert!(st.num_nodes() <= 2 * n + 1000 * 9);
+    }
+
+    #[test]
+    fn test_kth_smallest() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let nums: Vec<i64> = (0..n).map(|_| rng.gen_range(-50..50)).collect();
+        let kth = KthSmallest::new(&nums);
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (min(v1, v2), max(v1, v2));
+            let mut sorted = nums[l..=r].to_vec();
+            sorted.sort();
+
+            let k = rng.gen_range(0..sorted.len());
+            assert_eq!(kth.kth_smallest(l, r, k), Some(&sorted[k]));
+            assert_eq!(kth.kth_smallest(l, r, sorted.len()), None);
+
+            let x = rng.gen_range(-60..60);
+            let expected = sorted.iter().filter(|&&v| v <= x).count();
+            assert_eq!(kth.count_le(l, r, &x), expected);
+        }
+    }
+}
@@ -1,216 +1,291 @@
+use crate::data_structures::monoid::Monoid;
 use std::cmp::{max, min};
 
-/// Segment Tree
-/// O(log n) time complexity for range queries and updates.
-/// The segment tree is a binary tree that stores the range of values in an array.
-/// The root of the tree stores the range of values from 0 to n-1
-
-pub struct SegmentTree<T: PartialEq + Copy> {
-    n: usize,
-    nums: Vec<T>,
-    st: Vec<Option<T>>,
-    lazy: Vec<Option<T>>,
-    cnq: Box<dyn Fn(T, T) -> T>,
+// Children of node `p` in the 1-indexed layout used by the segment trees.
+#[inline]
+pub(crate) fn l(p: usize) -> usize {
+    p << 1
 }
 
-impl<T: PartialEq + Copy> SegmentTree<T> {
-    #[inline]
-    fn l(p: usize) -> usize {
-        p << 1
-    }
-
-    #[inline]
-    fn r(p: usize) -> usize {
-        (p << 1) + 1
-    }
+#[inline]
+pub(crate) fn r(p: usize) -> usize {
+    (p << 1) + 1
+}
 
-    fn conquer(&self, a: Option<T>, b: Option<T>) -> Option<T> {
-        match (a, b) {
-            (None, None) => None,
-            (Some(x), None) => Some(x),
-            (None, Some(y)) => Some(y),
-            (Some(x), Some(y)) => Some((self.cnq)(x, y)),
-        }
-    }
+/// Segment Tree
+/// O(log n) time complexity for range queries and point updates.
+/// The segment tree is a binary tree that stores the aggregate of ranges of an array.
+/// The root of the tree stores the aggregate of values from 0 to n-1.
+/// Generic over a `Monoid`, so any associative operation with an identity can be used
+/// and the nodes may hold composite values (e.g. minimum together with its index).
+#[derive(Debug, Clone)]
+pub struct SegmentTree<M: Monoid> {
+    n: usize,
+    nums: Vec<M::S>,
+    st: Vec<M::S>,
+}
 
-    pub fn new(nums: Vec<T>, cnq: Box<dyn Fn(T, T) -> T>) -> Self {
+impl<M: Monoid> SegmentTree<M> {
+    pub fn new(nums: Vec<M::S>) -> Self {
         let n = nums.len();
 
-        SegmentTree {
+        let mut segment_tree = SegmentTree {
             n,
             nums,
-            st: vec![None; 4 * n],
-            lazy: vec![None; 4 * n],
-            cnq,
+            st: vec![M::identity(); 4 * n],
+        };
+
+        if n > 0 {
+            segment_tree.build_internal(1, 0, n - 1);
         }
+
+        segment_tree
     }
 
-    pub fn rmq(&mut self, i: usize, j: usize) -> Option<T> {
-        self.rmq_internal(1, 0, self.n - 1, i, j)
+    pub fn len(&self) -> usize {
+        self.n
     }
 
-    pub fn build(&mut self) {
-        self.build_internal(1, 0, self.n - 1);
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
     }
 
-    pub fn update(&mut self, i: usize, j: usize, val: T) {
-        self.update_internal(1, 0, self.n - 1, i, j, val);
+    pub fn get(&self, i: usize) -> &M::S {
+        &self.nums[i]
     }
 
-    fn update_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize, val: T) {
-        self.propagate(p, left, right);
-        if i > j {
-            return;
+    /// Aggregate of the values in the inclusive range [i, j].
+    /// Returns the identity for an empty range.
+    pub fn query(&self, i: usize, j: usize) -> M::S {
+        if self.n == 0 {
+            return M::identity();
         }
 
-        if left >= i && right <= j {
-            self.lazy[p] = Some(val);
-            self.propagate(p, left, right);
-        } else {
-            let m = (right - left) / 2 + left;
-            self.update_internal(Self::l(p), left, m, i, min(m, j), val);
-            self.update_internal(Self::r(p), m + 1, right, max(i, m + 1), j, val);
-            let l_subtree = if Option::is_some(&self.lazy[Self::l(p)]) {
-                self.lazy[Self::l(p)].unwrap()
-            } else {
-                self.st[Self::l(p)].unwrap()
-            };
+        self.query_internal(1, 0, self.n - 1, i, min(j, self.n - 1))
+    }
 
-            let r_subtree = if Option::is_some(&self.lazy[Self::r(p)]) {
-                self.lazy[Self::r(p)].unwrap()
-            } else {
-                self.st[Self::r(p)].unwrap()
-            };
+    pub fn set(&mut self, i: usize, val: M::S) {
+        self.set_internal(1, 0, self.n - 1, i, val);
+    }
 
-            self.st[p] = if (self.cnq)(r_subtree, l_subtree) == l_subtree {
-                self.st[Self::l(p)]
-            } else {
-                self.st[Self::r(p)]
-            }
+    /// Largest `r` such that `pred` holds for the aggregate of the half-open range [l, r).
+    /// `pred` has to be monotone (once false it stays false as the range grows)
+    /// and hold for the identity. Runs in O(log n).
+    pub fn max_right<F>(&self, l: usize, pred: F) -> usize
+    where
+        F: Fn(&M::S) -> bool,
+    {
+        if l >= self.n {
+            return self.n;
+        }
+
+        let mut acc = M::identity();
+        self.max_right_internal(1, 0, self.n - 1, l, &pred, &mut acc)
+            .unwrap_or(self.n)
+    }
+
+    /// Smallest `l` such that `pred` holds for the aggregate of the half-open range [l, r).
+    /// `pred` has to be monotone (once false it stays false as the range grows)
+    /// and hold for the identity. Runs in O(log n).
+    pub fn min_left<F>(&self, r: usize, pred: F) -> usize
+    where
+        F: Fn(&M::S) -> bool,
+    {
+        if r == 0 || self.n == 0 {
+            return 0;
         }
+
+        let mut acc = M::identity();
+        self.min_left_internal(1, 0, self.n - 1, min(r, self.n) - 1, &pred, &mut acc)
+            .map_or(0, |i| i + 1)
     }
 
     fn build_internal(&mut self, p: usize, left: usize, right: usize) {
         if left == right {
-            self.st[p] = Some(self.nums[left]);
+            self.st[p] = self.nums[left].clone();
         } else {
             let m = (right - left) / 2 + left;
-            self.build_internal(Self::l(p), left, m);
-            self.build_internal(Self::r(p), m + 1, right);
-            self.st[p] = self.conquer(self.st[Self::l(p)], self.st[Self::r(p)]);
+            self.build_internal(l(p), left, m);
+            self.build_internal(r(p), m + 1, right);
+            self.st[p] = M::combine(&self.st[l(p)], &self.st[r(p)]);
         }
     }
 
-    fn propagate(&mut self, p: usize, left: usize, right: usize) {
-        if Option::is_some(&self.lazy[p]) {
-            self.st[p] = self.lazy[p];
-            if left != right {
-                self.lazy[Self::l(p)] = self.lazy[p];
-                self.lazy[Self::r(p)] = self.lazy[p];
-            } else {
-                self.nums[left] = self.lazy[p].unwrap_or_else(|| self.nums[left]);
+    fn set_internal(&mut self, p: usize, left: usize, right: usize, i: usize, val: M::S) {
+        if left == right {
+            self.nums[left] = val.clone();
+            self.st[p] = val;
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        if i <= m {
+            self.set_internal(l(p), left, m, i, val);
+        } else {
+            self.set_internal(r(p), m + 1, right, i, val);
+        }
+        self.st[p] = M::combine(&self.st[l(p)], &self.st[r(p)]);
+    }
+
+    // Returns the first index at or after `start` whose inclusion breaks `pred`.
+    fn max_right_internal<F>(
+        &self,
+        p: usize,
+        left: usize,
+        right: usize,
+        start: usize,
+        pred: &F,
+        acc: &mut M::S,
+    ) -> Option<usize>
+    where
+        F: Fn(&M::S) -> bool,
+    {
+        if right < start {
+            return None;
+        }
+
+        if left >= start {
+            let combined = M::combine(acc, &self.st[p]);
+            if pred(&combined) {
+                *acc = combined;
+                return None;
+            }
+
+            if left == right {
+                return Some(left);
             }
         }
+
+        let m = (right - left) / 2 + left;
+        self.max_right_internal(l(p), left, m, start, pred, acc)
+            .or_else(|| self.max_right_internal(r(p), m + 1, right, start, pred, acc))
     }
 
-    fn rmq_internal(
-        &mut self,
+    // Returns the last index at or before `end` whose inclusion breaks `pred`.
+    fn min_left_internal<F>(
+        &self,
         p: usize,
         left: usize,
         right: usize,
-        i: usize,
-        j: usize,
-    ) -> Option<T> {
-        self.propagate(p, left, right);
-        if i > j {
+        end: usize,
+        pred: &F,
+        acc: &mut M::S,
+    ) -> Option<usize>
+    where
+        F: Fn(&M::S) -> bool,
+    {
+        if left > end {
             return None;
         }
 
+        if right <= end {
+            let combined = M::combine(&self.st[p], acc);
+            if pred(&combined) {
+                *acc = combined;
+                return None;
+            }
+
+            if left == right {
+                return Some(left);
+            }
+        }
+
+        let m = (right - left) / 2 + left;
+        self.min_left_internal(r(p), m + 1, right, end, pred, acc)
+            .or_else(|| self.min_left_internal(l(p), left, m, end, pred, acc))
+    }
+
+    fn query_internal(&self, p: usize, left: usize, right: usize, i: usize, j: usize) -> M::S {
+        if i > j {
+            return M::identity();
+        }
+
         if (left >= i) && (right <= j) {
-            return self.st[p];
+            return self.st[p].clone();
         }
 
         let m: usize = (right - left) / 2 + left;
-        let res_left = self.rmq_internal(Self::l(p), left, m, i, min(m, j));
-        let res_right = self.rmq_internal(Self::r(p), m + 1, right, max(i, m + 1), j);
-        self.conquer(res_left, res_right)
+        let res_left = self.query_internal(l(p), left, m, i, min(m, j));
+        let res_right = self.query_internal(r(p), m + 1, right, max(i, m + 1), j);
+        M::combine(&res_left, &res_right)
+    }
+}
+
+impl<M: Monoid> From<Vec<M::S>> for SegmentTree<M> {
+    fn from(nums: Vec<M::S>) -> Self {
+        Self::new(nums)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::SegmentTree;
+    use crate::data_structures::monoid::{
+        Gcd, MatrixProduct, Max, Min, MinWithIndex, Monoid, Sum, Xor,
+    };
     use rand::prelude::*;
-    use std::cmp::min;
+    use std::cmp::{max, min};
 
     #[test]
     fn test_sparse_table_min() {
         let nums = [18, 17, 13, 19, 15, 11, 20, 99];
-        let cnq = |a, b| min(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
+        let st = SegmentTree::<Min<i32>>::new(nums.to_vec());
 
-        let res = st.rmq(1, 3);
-        assert_eq!(res, Some(13));
+        let res = st.query(1, 3);
+        assert_eq!(res, 13);
 
-        let res = st.rmq(4, 7);
-        assert_eq!(res, Some(11));
+        let res = st.query(4, 7);
+        assert_eq!(res, 11);
 
-        let res = st.rmq(3, 4);
-        assert_eq!(res, Some(15));
+        let res = st.query(3, 4);
+        assert_eq!(res, 15);
     }
 
     #[test]
     fn test_sparse_table_max() {
         let nums = [18, 17, 13, 19, 15, 11, 20, 99];
-        let cnq = |a, b| std::cmp::max(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
+        let st = SegmentTree::<Max<i32>>::new(nums.to_vec());
 
-        let res = st.rmq(1, 3);
-        assert_eq!(res, Some(19));
+        let res = st.query(1, 3);
+        assert_eq!(res, 19);
 
-        let res = st.rmq(4, 7);
-        assert_eq!(res, Some(99));
+        let res = st.query(4, 7);
+        assert_eq!(res, 99);
 
-        let res = st.rmq(3, 4);
-        assert_eq!(res, Some(19));
+        let res = st.query(3, 4);
+        assert_eq!(res, 19);
     }
 
     #[test]
     fn test_sparse_table_updates_min() {
         let nums = [18, 17, 13, 19, 15, 11, 20, 99];
-        let cnq = |a, b| min(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
-        st.update(5, 5, 40);
+        let mut st = SegmentTree::<Min<i32>>::new(nums.to_vec());
+        st.set(5, 40);
 
-        let res = st.rmq(1, 3);
-        assert_eq!(res, Some(13));
+        let res = st.query(1, 3);
+        assert_eq!(res, 13);
 
-        let res = st.rmq(4, 7);
-        assert_eq!(res, Some(15));
+        let res = st.query(4, 7);
+        assert_eq!(res, 15);
 
-        let res = st.rmq(3, 4);
-        assert_eq!(res, Some(15));
+        let res = st.query(3, 4);
+        assert_eq!(res, 15);
     }
 
     #[test]
     fn test_sparse_table_updates_max() {
         let nums = [18, 17, 13, 19, 15, 11, 20, 99];
-        let cnq = |a, b| std::cmp::max(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
-        st.update(5, 5, 120);
+        let mut st = SegmentTree::<Max<i32>>::new(nums.to_vec());
+        st.set(5, 120);
 
-        let res = st.rmq(1, 3);
-        assert_eq!(res, Some(19));
+        let res = st.query(1, 3);
+        assert_eq!(res, 19);
 
-        let res = st.rmq(4, 7);
-        assert_eq!(res, Some(120));
+        let res = st.query(4, 7);
+        assert_eq!(res, 120);
 
-        let res = st.rmq(3, 4);
-        assert_eq!(res, Some(19));
+        let res = st.query(3, 4);
+        assert_eq!(res, 19);
     }
 
     #[test]
@@ -220,41 +295,169 @@ mod tests {
         let mut nums: Vec<i32> = vec![0; n];
         let mut rng = rand::thread_rng();
 
-        for i in 0..n {
-            nums[i] = rng.gen_range(0..1000);
+        for num in nums.iter_mut() {
+            *num = rng.gen_range(0..1000);
         }
 
-        let cnq = |a, b| min(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
+        let st = SegmentTree::<Min<i32>>::new(nums.to_vec());
 
         for _ in 0..10_000 {
             let v1 = rng.gen_range(1..n);
             let v2 = rng.gen_range(1..n);
 
             let l = min(v1, v2);
-            let r = min(v1, v2);
+            let r = max(v1, v2);
 
-            let res = st.rmq(l, r);
-            let expected = nums[l..=r].iter().min().copied();
+            let res = st.query(l, r);
+            let expected = nums[l..=r].iter().min().copied().unwrap();
             assert_eq!(res, expected);
         }
     }
 
     #[test]
     fn test_sparse_table_strings() {
+        struct MinStr;
+
+        impl Monoid for MinStr {
+            type S = Option<&'static str>;
+
+            fn identity() -> Self::S {
+                None
+            }
+
+            fn combine(a: &Self::S, b: &Self::S) -> Self::S {
+                match (a, b) {
+                    (Some(x), Some(y)) => Some(min(*x, *y)),
+                    _ => a.or(*b),
+                }
+            }
+        }
+
         let nums = ["aaa", "be", "cc", "fdddd", "ed", "fa", "gasdasd", "h"];
-        let cnq = |a, b| min(a, b);
-        let mut st = SegmentTree::new(nums.to_vec(), Box::new(cnq));
-        st.build();
+        let st = SegmentTree::<MinStr>::new(nums.iter().copied().map(Some).collect());
 
-        let res = st.rmq(1, 3);
+        let res = st.query(1, 3);
         assert_eq!(res, Some("be"));
 
-        let res = st.rmq(4, 7);
+        let res = st.query(4, 7);
         assert_eq!(res, Some("ed"));
 
-        let res = st.rmq(3, 4);
+        let res = st.query(3, 4);
         assert_eq!(res, Some("ed"));
     }
+
+    #[test]
+    fn test_segment_tree_random_updates() {
+        let n = 500;
+        let mut rng = rand::thread_rng();
+        let mut nums: Vec<i64> = (0..n).map(|_| rng.gen_range(0..1000)).collect();
+
+        let mut sum = SegmentTree::<Sum<i64>>::new(nums.clone());
+        let mut gcd = SegmentTree::<Gcd<i64>>::new(nums.clone());
+        let mut xor = SegmentTree::<Xor<i64>>::new(nums.clone());
+        let mut argmin = SegmentTree::<MinWithIndex<i64>>::from(
+            nums.iter()
+                .enumerate()
+                .map(|(i, &x)| (x, i))
+                .collect::<Vec<_>>(),
+        );
+
+        for _ in 0..2000 {
+            if rng.gen_bool(0.5) {
+                let i = rng.gen_range(0..n);
+                let val = rng.gen_range(0..1000);
+                nums[i] = val;
+                sum.set(i, val);
+                gcd.set(i, val);
+                xor.set(i, val);
+                argmin.set(i, (val, i));
+                assert_eq!(*sum.get(i), val);
+            } else {
+                let v1 = rng.gen_range(0..n);
+                let v2 = rng.gen_range(0..n);
+                let l = min(v1, v2);
+                let r = max(v1, v2);
+
+                let range = &nums[l..=r];
+                assert_eq!(sum.query(l, r), range.iter().sum::<i64>());
+                assert_eq!(
+                    gcd.query(l, r),
+                    range.iter().fold(0, |acc, &x| num::Integer::gcd(&acc, &x))
+                );
+                assert_eq!(xor.query(l, r), range.iter().fold(0, |acc, &x| acc ^ x));
+
+                let expected = (l..=r).map(|i| (nums[i], i)).min().unwrap();
+                assert_eq!(argmin.query(l, r), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment_tree_matrix_product() {
+        type M = MatrixProduct<i64, 2>;
+        let nums = vec![[[1, 2], [3, 4]], [[0, 1], [1, 0]], [[2, 0], [0, 2]]];
+        let st = SegmentTree::<M>::new(nums.clone());
+
+        assert_eq!(
+            st.query(0, 2),
+            M::combine(&M::combine(&nums[0], &nums[1]), &nums[2])
+        );
+        assert_eq!(st.query(1, 1), nums[1]);
+        assert_eq!(st.query(2, 1), M::identity());
+    }
+
+    #[test]
+    fn test_segment_tree_max_right_min_left() {
+        let n = 300;
+        let mut rng = rand::thread_rng();
+        let nums: Vec<i64> = (0..n).map(|_| rng.gen_range(0..100)).collect();
+        let sum = SegmentTree::<Sum<i64>>::new(nums.clone());
+        let min_st = SegmentTree::<Min<i64>>::new(nums.clone());
+
+        for _ in 0..2000 {
+            let i = rng.gen_range(0..=n);
+            let k = rng.gen_range(0..3000);
+
+            // longest prefix of [i, n) with sum <= k
+            let expected = (i..=n)
+                .take_while(|&r| nums[i..r].iter().sum::<i64>() <= k)
+                .last()
+                .unwrap();
+            assert_eq!(sum.max_right(i, |&s| s <= k), expected);
+
+            // longest suffix of [0, i) with sum <= k
+            let expected = (0..=i)
+                .rev()
+                .take_while(|&l| nums[l..i].iter().sum::<i64>() <= k)
+                .last()
+                .unwrap();
+            assert_eq!(sum.min_left(i, |&s| s <= k), expected);
+
+            // first position at or after i with value < k / 30
+            let bound = k / 30;
+            let expected = (i..n).find(|&j| nums[j] < bound).unwrap_or(n);
+            assert_eq!(min_st.max_right(i, |&m| m >= bound), expected);
+        }
+    }
+
+    #[test]
+    fn test_segment_tree_kth_one() {
+        let bits = vec![0, 1, 1, 0, 0, 1, 0, 1];
+        let st = SegmentTree::<Sum<i32>>::new(bits);
+
+        // index of the k-th (0-based) one is the end of the longest prefix with sum <= k
+        let kth = |k: i32| st.max_right(0, |&s| s <= k);
+        assert_eq!(kth(0), 1);
+        assert_eq!(kth(1), 2);
+        assert_eq!(kth(2), 5);
+        assert_eq!(kth(3), 7);
+        assert_eq!(kth(4), 8);
+    }
+
+    #[test]
+    fn test_segment_tree_empty() {
+        let st = SegmentTree::<Sum<i32>>::new(vec![]);
+        assert!(st.is_empty());
+        assert_eq!(st.query(0, 0), 0);
+    }
 }
@@ -0,0 +1,342 @@
+use crate::data_structures::segment_tree::{l, r};
+use std::cmp::{max, min};
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    sum: i64,
+    max1: i64,
+    max2: i64,
+    max_cnt: i64,
+    min1: i64,
+    min2: i64,
+    min_cnt: i64,
+    lazy: i64,
+}
+
+impl Node {
+    fn leaf(val: i64) -> Self {
+        Node {
+            sum: val,
+            max1: val,
+            max2: i64::MIN,
+            max_cnt: 1,
+            min1: val,
+            min2: i64::MAX,
+            min_cnt: 1,
+            lazy: 0,
+        }
+    }
+
+    fn merge(a: &Node, b: &Node) -> Self {
+        let (max1, max2, max_cnt) = match a.max1.cmp(&b.max1) {
+            std::cmp::Ordering::Greater => (a.max1, max(a.max2, b.max1), a.max_cnt),
+            std::cmp::Ordering::Less => (b.max1, max(a.max1, b.max2), b.max_cnt),
+            std::cmp::Ordering::Equal => (a.max1, max(a.max2, b.max2), a.max_cnt + b.max_cnt),
+        };
+
+        let (min1, min2, min_cnt) = match a.min1.cmp(&b.min1) {
+            std::cmp::Ordering::Less => (a.min1, min(a.min2, b.min1), a.min_cnt),
+            std::cmp::Ordering::Greater => (b.min1, min(a.min1, b.min2), b.min_cnt),
+            std::cmp::Ordering::Equal => (a.min1, min(a.min2, b.min2), a.min_cnt + b.min_cnt),
+        };
+
+        Node {
+            sum: a.sum + b.sum,
+            max1,
+            max2,
+            max_cnt,
+            min1,
+            min2,
+            min_cnt,
+            lazy: 0,
+        }
+    }
+}
+
+/// Segment Tree Beats (Ji Ruyi's segment tree)
+/// Supports range chmin (a[i] = min(a[i], x)), range chmax (a[i] = max(a[i], x)),
+/// range add and range sum/min/max queries in amortized O(log^2 n).
+/// Every node keeps the maximum, the strict second maximum and the count of the maximum
+/// (and symmetrically for the minimum), so a chmin that only affects the maximums of
+/// a segment can be applied to the whole segment without descending further.
+#[derive(Debug, Clone)]
+pub struct SegmentTreeBeats {
+    n: usize,
+    st: Vec<Node>,
+}
+
+impl SegmentTreeBeats {
+    pub fn new(nums: &[i64]) -> Self {
+        let n = nums.len();
+        let mut segment_tree = SegmentTreeBeats {
+            n,
+            st: vec![Node::leaf(0); 4 * n],
+        };
+
+        if n > 0 {
+            segment_tree.build_internal(1, 0, n - 1, nums);
+        }
+
+        segment_tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// a[k] = min(a[k], x) for k in [i, j]
+    pub fn chmin(&mut self, i: usize, j: usize, x: i64) {
+        if i <= j && j < self.n {
+            self.chmin_internal(1, 0, self.n - 1, i, j, x);
+        }
+    }
+
+    /// a[k] = max(a[k], x) for k in [i, j]
+    pub fn chmax(&mut self, i: usize, j: usize, x: i64) {
+        if i <= j && j < self.n {
+            self.chmax_internal(1, 0, self.n - 1, i, j, x);
+        }
+    }
+
+    /// a[k] += x for k in [i, j]
+    pub fn add(&mut self, i: usize, j: usize, x: i64) {
+        if i <= j && j < self.n {
+            self.add_internal(1, 0, self.n - 1, i, j, x);
+        }
+    }
+
+    pub fn sum(&mut self, i: usize, j: usize) -> i64 {
+        if i > j || j >= self.n {
+            return 0;
+        }
+
+        self.query_internal(1, 0, self.n - 1, i, j).sum
+    }
+
+    pub fn max(&mut self, i: usize, j: usize) -> Option<i64> {
+        if i > j || j >= self.n {
+            return None;
+        }
+
+        Some(self.query_internal(1, 0, self.n - 1, i, j).max1)
+    }
+
+    pub fn min(&mut self, i: usize, j: usize) -> Option<i64> {
+        if i > j || j >= self.n {
+            return None;
+        }
+
+        Some(self.query_internal(1, 0, self.n - 1, i, j).min1)
+    }
+
+    fn build_internal(&mut self, p: usize, left: usize, right: usize, nums: &[i64]) {
+        if left == right {
+            self.st[p] = Node::leaf(nums[left]);
+        } else {
+            let m = (right - left) / 2 + left;
+            self.build_internal(l(p), left, m, nums);
+            self.build_internal(r(p), m + 1, right, nums);
+            self.pull(p);
+        }
+    }
+
+    fn pull(&mut self, p: usize) {
+        self.st[p] = Node::merge(&self.st[l(p)], &self.st[r(p)]);
+    }
+
+    fn apply_add(&mut self, p: usize, len: usize, x: i64) {
+        let node = &mut self.st[p];
+        node.sum += x * len as i64;
+        node.max1 += x;
+        node.min1 += x;
+        if node.max2 != i64::MIN {
+            node.max2 += x;
+        }
+        if node.min2 != i64::MAX {
+            node.min2 += x;
+        }
+        node.lazy += x;
+    }
+
+    // Lowers the maximums of a node to x, requires max2 < x < max1.
+    fn apply_chmin(&mut self, p: usize, x: i64) {
+        let node = &mut self.st[p];
+        node.sum -= (node.max1 - x) * node.max_cnt;
+        if node.min1 == node.max1 {
+            node.min1 = x;
+        } else if node.min2 == node.max1 {
+            node.min2 = x;
+        }
+        node.max1 = x;
+    }
+
+    // Raises the minimums of a node to x, requires min1 < x < min2.
+    fn apply_chmax(&mut self, p: usize, x: i64) {
+        let node = &mut self.st[p];
+        node.sum += (x - node.min1) * node.min_cnt;
+        if node.max1 == node.min1 {
+            node.max1 = x;
+        } else if node.max2 == node.min1 {
+            node.max2 = x;
+        }
+        node.min1 = x;
+    }
+
+    fn propagate(&mut self, p: usize, left: usize, right: usize) {
+        if left == right {
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        let lazy = std::mem::replace(&mut self.st[p].lazy, 0);
+        if lazy != 0 {
+            self.apply_add(l(p), m - left + 1, lazy);
+            self.apply_add(r(p), right - m, lazy);
+        }
+
+        let (max1, min1) = (self.st[p].max1, self.st[p].min1);
+        for c in [l(p), r(p)] {
+            if self.st[c].max1 > max1 {
+                self.apply_chmin(c, max1);
+            }
+            if self.st[c].min1 < min1 {
+                self.apply_chmax(c, min1);
+            }
+        }
+    }
+
+    fn chmin_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize, x: i64) {
+        if right < i || left > j || self.st[p].max1 <= x {
+            return;
+        }
+
+        if left >= i && right <= j && self.st[p].max2 < x {
+            self.apply_chmin(p, x);
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        self.chmin_internal(l(p), left, m, i, j, x);
+        self.chmin_internal(r(p), m + 1, right, i, j, x);
+        self.pull(p);
+    }
+
+    fn chmax_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize, x: i64) {
+        if right < i || left > j || self.st[p].min1 >= x {
+            return;
+        }
+
+        if left >= i && right <= j && self.st[p].min2 > x {
+            self.apply_chmax(p, x);
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        self.chmax_internal(l(p), left, m, i, j, x);
+        self.chmax_internal(r(p), m + 1, right, i, j, x);
+        self.pull(p);
+    }
+
+    fn add_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize, x: i64) {
+        if right < i || left > j {
+            return;
+        }
+
+        if left >= i && right <= j {
+            self.apply_add(p, right - left + 1, x);
+            return;
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        self.add_internal(l(p), left, m, i, j, x);
+        self.add_internal(r(p), m + 1, right, i, j, x);
+        self.pull(p);
+    }
+
+    fn query_internal(&mut self, p: usize, left: usize, right: usize, i: usize, j: usize) -> Node {
+        if left >= i && right <= j {
+            return self.st[p];
+        }
+
+        self.propagate(p, left, right);
+        let m = (right - left) / 2 + left;
+        if j <= m {
+            self.query_internal(l(p), left, m, i, j)
+        } else if i > m {
+            self.query_internal(r(p), m + 1, right, i, j)
+        } else {
+            let res_left = self.query_internal(l(p), left, m, i, j);
+            let res_right = self.query_internal(r(p), m + 1, right, i, j);
+            Node::merge(&res_left, &res_right)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SegmentTreeBeats;
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    #[test]
+    fn test_segment_tree_beats() {
+        let mut st = SegmentTreeBeats::new(&[5, 1, 8, 3, 9, 2]);
+
+        st.chmin(0, 5, 4);
+        assert_eq!(st.sum(0, 5), 4 + 1 + 4 + 3 + 4 + 2);
+        assert_eq!(st.max(0, 5), Some(4));
+
+        st.chmax(1, 3, 3);
+        assert_eq!(st.sum(0, 5), 4 + 3 + 4 + 3 + 4 + 2);
+        assert_eq!(st.min(0, 5), Some(2));
+        assert_eq!(st.min(0, 4), Some(3));
+
+        st.add(2, 5, 10);
+        assert_eq!(st.sum(2, 3), 14 + 13);
+        assert_eq!(st.max(0, 1), Some(4));
+        assert_eq!(st.max(3, 2), None);
+    }
+
+    #[test]
+    fn test_segment_tree_beats_random() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let mut nums: Vec<i64> = (0..n).map(|_| rng.gen_range(-1000..1000)).collect();
+        let mut st = SegmentTreeBeats::new(&nums);
+
+        for _ in 0..10_000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (min(v1, v2), max(v1, v2));
+            let x = rng.gen_range(-1000..1000);
+
+            match rng.gen_range(0..4) {
+                0 => {
+                    nums[l..=r].iter_mut().for_each(|v| *v = min(*v, x));
+                    st.chmin(l, r, x);
+                }
+                1 => {
+                    nums[l..=r].iter_mut().for_each(|v| *v = max(*v, x));
+                    st.chmax(l, r, x);
+                }
+                2 => {
+                    let x = x / 10;
+                    nums[l..=r].iter_mut().for_each(|v| *v += x);
+                    st.add(l, r, x);
+                }
+                _ => {
+                    assert_eq!(st.sum(l, r), nums[l..=r].iter().sum::<i64>());
+                    assert_eq!(st.max(l, r), nums[l..=r].iter().max().copied());
+                    assert_eq!(st.min(l, r), nums[l..=r].iter().min().copied());
+                }
+            }
+        }
+    }
+}
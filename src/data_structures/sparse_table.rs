@@ -1,104 +1,265 @@
-use num::Zero;
-use std::f64;
-use std::ops::AddAssign;
+use crate::data_structures::monoid::{Idempotent, Monoid};
 
+/// Sparse Table
+/// st[i][j] holds the aggregate of the 2^i values starting at j, O(n log n) preprocessing.
+/// For idempotent operations (min, max, gcd, and, or, argmin) a range is covered by two
+/// overlapping blocks, so `query` runs in O(1). Other monoids can still use `fold`,
+/// which splits the range into O(log n) disjoint blocks.
 #[derive(Debug, Clone)]
-pub enum SparseTableType {
-    MIN,
-    MAX,
-    SUM,
+pub struct SparseTable<M: Monoid> {
+    st: Vec<Vec<M::S>>,
+    log: Vec<usize>,
 }
 
-#[derive(Debug, Clone)]
-pub struct SparseTable<T>
-where
-    T: Ord + Zero + Copy + AddAssign,
-{
-    pub st: Vec<Vec<T>>,
-    pub nums: Vec<T>,
-    pub k: usize,
-    pub st_type: SparseTableType,
-}
+/// log[i] = floor(log2(i)) for i in 1..=n
+fn log_table(n: usize) -> Vec<usize> {
+    let mut log = vec![0; n + 1];
+    for i in 2..=n {
+        log[i] = log[i / 2] + 1;
+    }
 
-impl<T> SparseTable<T>
-where
-    T: Ord + Zero + Copy + AddAssign,
-{
-    pub fn new(nums: Vec<T>, st_type: SparseTableType) -> Self {
-        let k = f64::log2(nums.len() as f64) as usize;
-        let mut st = vec![vec![T::zero(); nums.len()]; k + 1];
+    log
+}
 
-        st[0][..nums.len()].copy_from_slice(&nums[..]);
+impl<M: Monoid> SparseTable<M> {
+    pub fn new(nums: Vec<M::S>) -> Self {
+        let n = nums.len();
+        let log = log_table(n);
+        let k = log[n];
+        let mut st = Vec::with_capacity(k + 1);
+        st.push(nums);
 
         for i in 1..=k {
-            let mut j = 0;
-            while j + (1 << i) <= nums.len() {
-                st[i][j] = match st_type {
-                    SparseTableType::MIN => st[i - 1][j].min(st[i - 1][j + (1 << (i - 1))]),
-                    SparseTableType::MAX => st[i - 1][j].max(st[i - 1][j + (1 << (i - 1))]),
-                    SparseTableType::SUM => st[i - 1][j] + st[i - 1][j + (1 << (i - 1))],
-                };
-                j += 1;
-            }
+            let prev = &st[i - 1];
+            let half = 1 << (i - 1);
+            let level = (0..=n - (1 << i))
+                .map(|j| M::combine(&prev[j], &prev[j + half]))
+                .collect();
+            st.push(level);
         }
 
-        Self {
-            st,
-            nums,
-            k,
-            st_type,
-        }
+        Self { st, log }
+    }
+
+    pub fn from_fn<F>(n: usize, f: F) -> Self
+    where
+        F: FnMut(usize) -> M::S,
+    {
+        Self::new((0..n).map(f).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.st[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.st[0].is_empty()
     }
 
-    pub fn sum_query(&self, l: usize, r: usize) -> T {
-        let mut sum = T::zero();
+    /// Aggregate of the inclusive range [l, r] from O(log n) disjoint blocks,
+    /// works for any monoid.
+    pub fn fold(&self, l: usize, r: usize) -> M::S {
+        let mut res = M::identity();
         let mut j = l;
-        for i in (0..=self.k).rev() {
+        for i in (0..self.st.len()).rev() {
             if j <= r && (1 << i) <= r - j + 1 {
-                sum += self.st[i][j];
+                res = M::combine(&res, &self.st[i][j]);
                 j += 1 << i;
             }
         }
 
-        sum
+        res
     }
+}
 
-    pub fn min_query(&self, l: usize, r: usize) -> T {
-        let i = f64::log2((r - l + 1) as f64) as usize;
-        self.st[i][l].min(self.st[i][r - ((1 << i) - 1)])
+impl<M: Idempotent> SparseTable<M> {
+    /// Aggregate of the inclusive range [l, r] in O(1).
+    pub fn query(&self, l: usize, r: usize) -> M::S {
+        let i = self.log[r - l + 1];
+        M::combine(&self.st[i][l], &self.st[i][r + 1 - (1 << i)])
     }
+}
+
+/// 2D Sparse Table
+/// st[a][b][i][j] holds the aggregate of the 2^a x 2^b rectangle with the top-left corner
+/// at (i, j). O(nm log n log m) preprocessing and O(1) rectangle queries for idempotent
+/// operations, where four overlapping rectangles cover the queried one.
+/// All methods take the coordinates in (row, col) order.
+#[derive(Debug, Clone)]
+pub struct SparseTable2D<M: Idempotent> {
+    st: Vec<Vec<Vec<Vec<M::S>>>>,
+    log_rows: Vec<usize>,
+    log_cols: Vec<usize>,
+}
 
-    pub fn max_query(&self, l: usize, r: usize) -> T {
-        let i = f64::log2((r - l + 1) as f64) as usize;
-        self.st[i][l].max(self.st[i][r - ((1 << i) - 1)])
+impl<M: Idempotent> SparseTable2D<M> {
+    pub fn new(grid: Vec<Vec<M::S>>) -> Self {
+        let rows = grid.len();
+        let cols = grid.first().map_or(0, |row| row.len());
+        let log_rows = log_table(rows);
+        let log_cols = log_table(cols);
+
+        // first row of levels: 1 x 2^b rectangles
+        let mut first = vec![grid];
+        for b in 1..=log_cols[cols] {
+            let prev = &first[b - 1];
+            let half = 1 << (b - 1);
+            let level = prev
+                .iter()
+                .map(|row| {
+                    (0..=cols - (1 << b))
+                        .map(|j| M::combine(&row[j], &row[j + half]))
+                        .collect()
+                })
+                .collect();
+            first.push(level);
+        }
+
+        let mut st = vec![first];
+        for a in 1..=log_rows[rows] {
+            let half = 1 << (a - 1);
+            let levels = st[a - 1]
+                .iter()
+                .map(|prev: &Vec<Vec<M::S>>| {
+                    (0..=rows - (1 << a))
+                        .map(|i| {
+                            prev[i]
+                                .iter()
+                                .zip(prev[i + half].iter())
+                                .map(|(x, y)| M::combine(x, y))
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect();
+            st.push(levels);
+        }
+
+        SparseTable2D {
+            st,
+            log_rows,
+            log_cols,
+        }
+    }
+
+    pub fn from_fn<F>(rows: usize, cols: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize, usize) -> M::S,
+    {
+        Self::new(
+            (0..rows)
+                .map(|i| (0..cols).map(|j| f(i, j)).collect())
+                .collect(),
+        )
+    }
+
+    /// Aggregate of the rectangle [row1, row2] x [col1, col2] in O(1).
+    pub fn query(&self, row1: usize, col1: usize, row2: usize, col2: usize) -> M::S {
+        let a = self.log_rows[row2 - row1 + 1];
+        let b = self.log_cols[col2 - col1 + 1];
+        let level = &self.st[a][b];
+        let (i2, j2) = (row2 + 1 - (1 << a), col2 + 1 - (1 << b));
+
+        let top = M::combine(&level[row1][col1], &level[row1][j2]);
+        let bottom = M::combine(&level[i2][col1], &level[i2][j2]);
+        M::combine(&top, &bottom)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{SparseTable, SparseTableType};
+    use super::{SparseTable, SparseTable2D};
+    use crate::data_structures::monoid::{Gcd, Max, Min, MinWithIndex, Sum};
+    use rand::prelude::*;
 
     #[test]
     fn test_sparse_table_sum() {
         let nums = vec![1, 2, 3, 4, 5, 6, 7, 8];
-        let st = SparseTable::new(nums, SparseTableType::SUM);
-        let res = st.sum_query(0, 7);
-        print!("Sum query result: {}", res);
+        let st = SparseTable::<Sum<i32>>::new(nums);
+        let res = st.fold(0, 7);
+        assert_eq!(res, 36);
+        let res = st.fold(2, 4);
+        assert_eq!(res, 12);
     }
 
     #[test]
     fn test_sparse_table_min() {
         let nums = vec![1, 2, 3, 4, 5, 6, 7, 8];
-        let st = SparseTable::new(nums, SparseTableType::MIN);
-        let res = st.min_query(4, 7);
-        print!("Min query result: {}", res);
+        let st = SparseTable::<Min<i32>>::new(nums);
+        let res = st.query(4, 7);
+        assert_eq!(res, 5);
     }
 
     #[test]
     fn test_sparse_table_max() {
         let nums = vec![1, 2, 20, 4, 5, 6, 7, 8];
-        let st = SparseTable::new(nums, SparseTableType::MAX);
-        let res = st.max_query(0, 7);
-        print!("Max query result: {}", res);
+        let st = SparseTable::<Max<i32>>::new(nums);
+        let res = st.query(0, 7);
+        assert_eq!(res, 20);
+        let res = st.query(3, 7);
+        assert_eq!(res, 8);
+    }
+
+    #[test]
+    fn test_sparse_table_random() {
+        let n = 1000;
+        let mut rng = thread_rng();
+        let nums: Vec<i64> = (0..n).map(|_| rng.gen_range(1..1000) * 6).collect();
+
+        let gcd = SparseTable::<Gcd<i64>>::new(nums.clone());
+        let argmin = SparseTable::<MinWithIndex<i64>>::from_fn(n, |i| (nums[i], i));
+        let sum = SparseTable::<Sum<i64>>::new(nums.clone());
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (v1.min(v2), v1.max(v2));
+            let range = &nums[l..=r];
+
+            assert_eq!(
+                gcd.query(l, r),
+                range.iter().fold(0, |acc, &x| num::Integer::gcd(&acc, &x))
+            );
+            assert_eq!(
+                argmin.query(l, r),
+                (l..=r).map(|i| (nums[i], i)).min().unwrap()
+            );
+            assert_eq!(sum.fold(l, r), range.iter().sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn test_sparse_table_2_d() {
+        let grid = vec![vec![5, 3, 8], vec![2, 9, 7], vec![6, 4, 1], vec![0, 11, 10]];
+        let min = SparseTable2D::<Min<i32>>::new(grid.clone());
+        let max = SparseTable2D::<Max<i32>>::new(grid);
+
+        assert_eq!(min.query(0, 0, 1, 2), 2);
+        assert_eq!(min.query(0, 1, 2, 2), 1);
+        assert_eq!(max.query(1, 0, 2, 1), 9);
+        assert_eq!(max.query(3, 0, 3, 2), 11);
+        assert_eq!(max.query(0, 2, 0, 2), 8);
+    }
+
+    #[test]
+    fn test_sparse_table_2_d_random() {
+        let (rows, cols) = (23, 37);
+        let mut rng = thread_rng();
+        let grid: Vec<Vec<i64>> = (0..rows)
+            .map(|_| (0..cols).map(|_| rng.gen_range(-1000..1000)).collect())
+            .collect();
+        let min = SparseTable2D::<Min<i64>>::from_fn(rows, cols, |i, j| grid[i][j]);
+        let max = SparseTable2D::<Max<i64>>::new(grid.clone());
+
+        for _ in 0..1000 {
+            let (a, b) = (rng.gen_range(0..rows), rng.gen_range(0..rows));
+            let (c, d) = (rng.gen_range(0..cols), rng.gen_range(0..cols));
+            let (r1, r2) = (a.min(b), a.max(b));
+            let (c1, c2) = (c.min(d), c.max(d));
+            let cells = grid[r1..=r2].iter().flat_map(|row| row[c1..=c2].iter());
+
+            assert_eq!(min.query(r1, c1, r2, c2), *cells.clone().min().unwrap());
+            assert_eq!(max.query(r1, c1, r2, c2), *cells.max().unwrap());
+        }
     }
 }
@@ -0,0 +1,41 @@
+use num::Zero;
+use std::ops::{Add, AddAssign, Sub};
+
+pub(crate) const MOD: u64 = 1_000_000_007;
+
+/// Integer modulo `MOD` with only the operations of an additive group, to check that a
+/// structure does not rely on anything else of the primitive integers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ModInt(pub(crate) u64);
+
+impl Add for ModInt {
+    type Output = ModInt;
+
+    fn add(self, other: ModInt) -> ModInt {
+        ModInt((self.0 + other.0) % MOD)
+    }
+}
+
+impl AddAssign for ModInt {
+    fn add_assign(&mut self, other: ModInt) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for ModInt {
+    type Output = ModInt;
+
+    fn sub(self, other: ModInt) -> ModInt {
+        ModInt((self.0 + MOD - other.0) % MOD)
+    }
+}
+
+impl Zero for ModInt {
+    fn zero() -> ModInt {
+        ModInt(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
@@ -0,0 +1,933 @@
+use crate::data_structures::monoid::Monoid;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+// Index of the empty tree, node 0 of every arena is a sentinel.
+const NIL: usize = 0;
+
+/// xorshift64, priorities only need to be independent of the input
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+// Roots of the dropped sequences of a treap, their nodes are reused by the next insertion.
+// Also identifies the treap a sequence was detached from.
+type Dropped = Rc<RefCell<Vec<usize>>>;
+
+/// Handle to a sequence detached from an `ImplicitTreap` by `split_off` or `replace`.
+/// It can only be used with the treap it was detached from, its nodes are reclaimed by the
+/// treap when it is dropped.
+#[derive(Debug)]
+pub struct Sequence {
+    root: usize,
+    owner: Dropped,
+}
+
+impl Sequence {
+    // takes the root out, so dropping the handle afterwards does not reclaim the nodes
+    fn take(mut self) -> usize {
+        std::mem::replace(&mut self.root, NIL)
+    }
+}
+
+impl Drop for Sequence {
+    fn drop(&mut self) {
+        if self.root != NIL {
+            self.owner.borrow_mut().push(self.root);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ImplicitNode<S> {
+    left: usize,
+    right: usize,
+    priority: u64,
+    size: usize,
+    val: S,
+    // aggregates of the subtree in order and in reversed order
+    agg: S,
+    rev_agg: S,
+    // the subtree still has to be reversed, the fields of this node are not updated yet
+    rev: bool,
+}
+
+/// Implicit Treap
+/// Randomized balanced binary tree keyed by position, so a sequence can be split and
+/// merged at any index in expected O(log n). This gives insertion and removal at any
+/// position, cut-and-paste of subarrays, range reversal and range aggregates over a monoid.
+/// Nodes live in an arena and removed nodes are reused. `split_off` detaches a part of the
+/// sequence into a `Sequence` handle in the same arena, so it can be appended back anywhere
+/// in expected O(log n) without copying.
+#[derive(Debug)]
+pub struct ImplicitTreap<M: Monoid> {
+    nodes: Vec<ImplicitNode<M::S>>,
+    free: Vec<usize>,
+    dropped: Dropped,
+    root: usize,
+    rng: Rng,
+}
+
+impl<M: Monoid> ImplicitTreap<M> {
+    pub fn new() -> Self {
+        ImplicitTreap {
+            nodes: vec![ImplicitNode {
+                left: NIL,
+                right: NIL,
+                priority: 0,
+                size: 0,
+                val: M::identity(),
+                agg: M::identity(),
+                rev_agg: M::identity(),
+                rev: false,
+            }],
+            free: Vec::new(),
+            dropped: Rc::default(),
+            root: NIL,
+            rng: Rng::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes[self.root].size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    /// Number of nodes in the arena, including the ones waiting to be reused.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn push_back(&mut self, val: M::S) {
+        let x = self.new_node(val);
+        self.root = self.merge(self.root, x);
+    }
+
+    /// Inserts `val` so that it ends up at index `pos`.
+    pub fn insert_at(&mut self, pos: usize, val: M::S) {
+        assert!(pos <= self.len(), "insertion index out of range");
+
+        let x = self.new_node(val);
+        let (a, b) = self.split(self.root, pos);
+        let a = self.merge(a, x);
+        self.root = self.merge(a, b);
+    }
+
+    pub fn remove_at(&mut self, pos: usize) -> Option<M::S> {
+        if pos >= self.len() {
+            return None;
+        }
+
+        let (a, b) = self.split(self.root, pos);
+        let (x, c) = self.split(b, 1);
+        self.root = self.merge(a, c);
+        self.free.push(x);
+
+        Some(std::mem::replace(&mut self.nodes[x].val, M::identity()))
+    }
+
+    pub fn get(&self, pos: usize) -> Option<&M::S> {
+        if pos >= self.len() {
+            return None;
+        }
+
+        // walk down with the parity of the pending reversals instead of pushing them
+        let mut x = self.root;
+        let mut pos = pos;
+        let mut flip = false;
+        loop {
+            flip ^= self.nodes[x].rev;
+            let (l, r) = if flip {
+                (self.nodes[x].right, self.nodes[x].left)
+            } else {
+                (self.nodes[x].left, self.nodes[x].right)
+            };
+
+            let left_size = self.nodes[l].size;
+            match pos.cmp(&left_size) {
+                Ordering::Less => x = l,
+                Ordering::Equal => return Some(&self.nodes[x].val),
+                Ordering::Greater => {
+                    pos -= left_size + 1;
+                    x = r;
+                }
+            }
+        }
+    }
+
+    pub fn set(&mut self, pos: usize, val: M::S) {
+        assert!(pos < self.len(), "index out of range");
+
+        let (a, b) = self.split(self.root, pos);
+        let (x, c) = self.split(b, 1);
+        self.nodes[x].val = val;
+        self.pull(x);
+        let b = self.merge(x, c);
+        self.root = self.merge(a, b);
+    }
+
+    /// Aggregate of the inclusive range [l, r].
+    pub fn query(&mut self, l: usize, r: usize) -> M::S {
+        if l > r || l >= self.len() {
+            return M::identity();
+        }
+
+        let (a, b) = self.split(self.root, l);
+        let (m, c) = self.split(b, r - l + 1);
+        let res = self.agg(m).clone();
+        let b = self.merge(m, c);
+        self.root = self.merge(a, b);
+
+        res
+    }
+
+    /// Reverses the inclusive range [l, r].
+    pub fn reverse(&mut self, l: usize, r: usize) {
+        if l >= r || l >= self.len() {
+            return;
+        }
+
+        let (a, b) = self.split(self.root, l);
+        let (m, c) = self.split(b, r - l + 1);
+        self.nodes[m].rev ^= true;
+        let b = self.merge(m, c);
+        self.root = self.merge(a, b);
+    }
+
+    /// Cuts out the inclusive range [l, r] and inserts it so that it starts at index `to`
+    /// of the resulting sequence.
+    pub fn move_range(&mut self, l: usize, r: usize, to: usize) {
+        assert!(l <= r && r < self.len(), "range out of bounds");
+        assert!(to + r - l < self.len(), "target index out of range");
+
+        let (a, b) = self.split(self.root, l);
+        let (m, c) = self.split(b, r - l + 1);
+        let rest = self.merge(a, c);
+        let (a, c) = self.split(rest, to);
+        let a = self.merge(a, m);
+        self.root = self.merge(a, c);
+    }
+
+    /// Detaches the elements from index `pos` on, like `Vec::split_off`, expected O(log n).
+    /// The detached elements stay in the arena of this treap.
+    pub fn split_off(&mut self, pos: usize) -> Sequence {
+        let (a, b) = self.split(self.root, pos.min(self.len()));
+        self.root = a;
+
+        self.detach(b)
+    }
+
+    /// Moves all elements of a detached sequence to the end, expected O(log n).
+    pub fn append(&mut self, other: Sequence) {
+        let other = self.attach(other);
+        self.root = self.merge(self.root, other);
+    }
+
+    /// Makes the detached sequence `other` the current one and detaches the current one, O(1).
+    pub fn replace(&mut self, other: Sequence) -> Sequence {
+        let other = self.attach(other);
+        let root = std::mem::replace(&mut self.root, other);
+        self.detach(root)
+    }
+
+    pub fn to_vec(&self) -> Vec<M::S> {
+        let mut values = Vec::with_capacity(self.len());
+        self.collect(self.root, false, &mut values);
+
+        values
+    }
+
+    fn detach(&self, x: usize) -> Sequence {
+        Sequence {
+            root: x,
+            owner: Rc::clone(&self.dropped),
+        }
+    }
+
+    fn attach(&self, seq: Sequence) -> usize {
+        assert!(
+            Rc::ptr_eq(&seq.owner, &self.dropped),
+            "sequence was detached from another treap"
+        );
+        seq.take()
+    }
+
+    fn new_node(&mut self, val: M::S) -> usize {
+        if self.free.is_empty() {
+            let dropped = std::mem::take(&mut *self.dropped.borrow_mut());
+            for x in dropped {
+                self.release(x);
+            }
+        }
+
+        let node = ImplicitNode {
+            left: NIL,
+            right: NIL,
+            priority: self.rng.next(),
+            size: 1,
+            agg: val.clone(),
+            rev_agg: val.clone(),
+            val,
+            rev: false,
+        };
+
+        match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, x: usize) {
+        if x == NIL {
+            return;
+        }
+
+        let (l, r) = (self.nodes[x].left, self.nodes[x].right);
+        self.release(l);
+        self.release(r);
+        self.free.push(x);
+    }
+
+    // in-order walk with the parity of the pending reversals, like `get`
+    fn collect(&self, x: usize, flip: bool, values: &mut Vec<M::S>) {
+        if x == NIL {
+            return;
+        }
+
+        let flip = flip ^ self.nodes[x].rev;
+        let (l, r) = if flip {
+            (self.nodes[x].right, self.nodes[x].left)
+        } else {
+            (self.nodes[x].left, self.nodes[x].right)
+        };
+        self.collect(l, flip, values);
+        values.push(self.nodes[x].val.clone());
+        self.collect(r, flip, values);
+    }
+
+    fn agg(&self, x: usize) -> &M::S {
+        if self.nodes[x].rev {
+            &self.nodes[x].rev_agg
+        } else {
+            &self.nodes[x].agg
+        }
+    }
+
+    fn rev_agg(&self, x: usize) -> &M::S {
+        if self.nodes[x].rev {
+            &self.nodes[x].agg
+        } else {
+            &self.nodes[x].rev_agg
+        }
+    }
+
+    fn push(&mut self, x: usize) {
+        if x == NIL || !self.nodes[x].rev {
+            return;
+        }
+
+        let node = &mut self.nodes[x];
+        node.rev = false;
+        std::mem::swap(&mut node.left, &mut node.right);
+        std::mem::swap(&mut node.agg, &mut node.rev_agg);
+        let (l, r) = (node.left, node.right);
+        if l != NIL {
+            self.nodes[l].rev ^= true;
+        }
+        if r != NIL {
+            self.nodes[r].rev ^= true;
+        }
+    }
+
+    // requires the node itself to be pushed
+    fn pull(&mut self, x: usize) {
+        let (l, r) = (self.nodes[x].left, self.nodes[x].right);
+        let val = &self.nodes[x].val;
+        let agg = M::combine(&M::combine(self.agg(l), val), self.agg(r));
+        let rev_agg = M::combine(&M::combine(self.rev_agg(r), val), self.rev_agg(l));
+        let size = self.nodes[l].size + self.nodes[r].size + 1;
+
+        let node = &mut self.nodes[x];
+        node.agg = agg;
+        node.rev_agg = rev_agg;
+        node.size = size;
+    }
+
+    // first `k` elements go to the left tree
+    fn split(&mut self, x: usize, k: usize) -> (usize, usize) {
+        if x == NIL {
+            return (NIL, NIL);
+        }
+
+        self.push(x);
+        let left_size = self.nodes[self.nodes[x].left].size;
+        if k <= left_size {
+            let (a, b) = self.split(self.nodes[x].left, k);
+            self.nodes[x].left = b;
+            self.pull(x);
+            (a, x)
+        } else {
+            let (a, b) = self.split(self.nodes[x].right, k - left_size - 1);
+            self.nodes[x].right = a;
+            self.pull(x);
+            (x, b)
+        }
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+
+        if self.nodes[a].priority > self.nodes[b].priority {
+            self.push(a);
+            let right = self.merge(self.nodes[a].right, b);
+            self.nodes[a].right = right;
+            self.pull(a);
+            a
+        } else {
+            self.push(b);
+            let left = self.merge(a, self.nodes[b].left);
+            self.nodes[b].left = left;
+            self.pull(b);
+            b
+        }
+    }
+}
+
+// a fresh arena, the nodes of detached sequences are not copied and the clone has its own
+// sequences
+impl<M: Monoid> Clone for ImplicitTreap<M> {
+    fn clone(&self) -> Self {
+        self.to_vec().into_iter().collect()
+    }
+}
+
+impl<M: Monoid> Default for ImplicitTreap<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Monoid> FromIterator<M::S> for ImplicitTreap<M> {
+    fn from_iter<I: IntoIterator<Item = M::S>>(iter: I) -> Self {
+        let mut treap = Self::new();
+        for val in iter {
+            treap.push_back(val);
+        }
+
+        treap
+    }
+}
+
+#[derive(Debug, Clone)]
+struct KeyedNode<K, V> {
+    left: usize,
+    right: usize,
+    priority: u64,
+    size: usize,
+    // `None` only for the sentinel and released nodes
+    entry: Option<(K, V)>,
+}
+
+/// Ordered map on a treap keyed by `K`, with order statistics:
+/// `rank` (number of smaller keys) and `kth` (k-th smallest key) in expected O(log n).
+#[derive(Debug, Clone)]
+pub struct TreapMap<K: Ord, V> {
+    nodes: Vec<KeyedNode<K, V>>,
+    free: Vec<usize>,
+    root: usize,
+    rng: Rng,
+}
+
+impl<K: Ord, V> TreapMap<K, V> {
+    pub fn new() -> Self {
+        TreapMap {
+            nodes: vec![KeyedNode {
+                left: NIL,
+                right: NIL,
+                priority: 0,
+                size: 0,
+                entry: None,
+            }],
+            free: Vec::new(),
+            root: NIL,
+            rng: Rng::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes[self.root].size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    fn key(&self, x: usize) -> &K {
+        &self.nodes[x].entry.as_ref().unwrap().0
+    }
+
+    fn find(&self, key: &K) -> usize {
+        let mut x = self.root;
+        while x != NIL {
+            match key.cmp(self.key(x)) {
+                Ordering::Less => x = self.nodes[x].left,
+                Ordering::Equal => return x,
+                Ordering::Greater => x = self.nodes[x].right,
+            }
+        }
+
+        NIL
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.nodes[self.find(key)].entry.as_ref().map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let x = self.find(key);
+        self.nodes[x].entry.as_mut().map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key) != NIL
+    }
+
+    /// Inserts the entry and returns the previous value of the key.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let x = self.find(&key);
+        if x != NIL {
+            return self.nodes[x]
+                .entry
+                .as_mut()
+                .map(|(_, v)| std::mem::replace(v, val));
+        }
+
+        let node = KeyedNode {
+            left: NIL,
+            right: NIL,
+            priority: self.rng.next(),
+            size: 1,
+            entry: None,
+        };
+        let x = match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        let (a, b) = self.split(self.root, &key);
+        self.nodes[x].entry = Some((key, val));
+        let a = self.merge(a, x);
+        self.root = self.merge(a, b);
+
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let (a, b) = self.split(self.root, key);
+        let (x, c) = self.split_first(b);
+        self.root = self.merge(a, c);
+        self.free.push(x);
+
+        self.nodes[x].entry.take().map(|(_, v)| v)
+    }
+
+    /// Number of keys strictly less than `key`.
+    pub fn rank(&self, key: &K) -> usize {
+        let mut x = self.root;
+        let mut res = 0;
+        while x != NIL {
+            if self.key(x) < key {
+                res += self.nodes[self.nodes[x].left].size + 1;
+                x = self.nodes[x].right;
+            } else {
+                x = self.nodes[x].left;
+            }
+        }
+
+        res
+    }
+
+    /// k-th (0-based) smallest entry.
+    pub fn kth(&self, k: usize) -> Option<(&K, &V)> {
+        if k >= self.len() {
+            return None;
+        }
+
+        let mut x = self.root;
+        let mut k = k;
+        loop {
+            let left_size = self.nodes[self.nodes[x].left].size;
+            match k.cmp(&left_size) {
+                Ordering::Less => x = self.nodes[x].left,
+                Ordering::Equal => return self.nodes[x].entry.as_ref().map(|(k, v)| (k, v)),
+                Ordering::Greater => {
+                    k -= left_size + 1;
+                    x = self.nodes[x].right;
+                }
+            }
+        }
+    }
+
+    /// Entries in increasing order of the keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        // in-order walk, the stack holds the nodes whose right subtree is still to be visited
+        let mut stack = Vec::new();
+        let mut x = self.root;
+        std::iter::from_fn(move || {
+            while x != NIL {
+                stack.push(x);
+                x = self.nodes[x].left;
+            }
+
+            let y = stack.pop()?;
+            x = self.nodes[y].right;
+            self.nodes[y].entry.as_ref().map(|(k, v)| (k, v))
+        })
+    }
+
+    fn pull(&mut self, x: usize) {
+        let (l, r) = (self.nodes[x].left, self.nodes[x].right);
+        self.nodes[x].size = self.nodes[l].size + self.nodes[r].size + 1;
+    }
+
+    // keys less than `key` go to the left tree
+    fn split(&mut self, x: usize, key: &K) -> (usize, usize) {
+        if x == NIL {
+            return (NIL, NIL);
+        }
+
+        if self.key(x) < key {
+            let (a, b) = self.split(self.nodes[x].right, key);
+            self.nodes[x].right = a;
+            self.pull(x);
+            (x, b)
+        } else {
+            let (a, b) = self.split(self.nodes[x].left, key);
+            self.nodes[x].left = b;
+            self.pull(x);
+            (a, x)
+        }
+    }
+
+    // detaches the smallest node
+    fn split_first(&mut self, x: usize) -> (usize, usize) {
+        if self.nodes[x].left == NIL {
+            let r = self.nodes[x].right;
+            self.nodes[x].right = NIL;
+            self.pull(x);
+            return (x, r);
+        }
+
+        let (first, rest) = self.split_first(self.nodes[x].left);
+        self.nodes[x].left = rest;
+        self.pull(x);
+        (first, x)
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+
+        if self.nodes[a].priority > self.nodes[b].priority {
+            let right = self.merge(self.nodes[a].right, b);
+            self.nodes[a].right = right;
+            self.pull(a);
+            a
+        } else {
+            let left = self.merge(a, self.nodes[b].left);
+            self.nodes[b].left = left;
+            self.pull(b);
+            b
+        }
+    }
+}
+
+impl<K: Ord, V> Default for TreapMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Ordered set on a treap with `rank` and `kth`.
+#[derive(Debug, Clone)]
+pub struct TreapSet<K: Ord> {
+    map: TreapMap<K, ()>,
+}
+
+impl<K: Ord> TreapSet<K> {
+    pub fn new() -> Self {
+        TreapSet {
+            map: TreapMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns whether the key was newly inserted.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Number of keys strictly less than `key`.
+    pub fn rank(&self, key: &K) -> usize {
+        self.map.rank(key)
+    }
+
+    /// k-th (0-based) smallest key.
+    pub fn kth(&self, k: usize) -> Option<&K> {
+        self.map.kth(k).map(|(k, _)| k)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &K> + '_ {
+        self.map.iter().map(|(k, _)| k)
+    }
+}
+
+impl<K: Ord> Default for TreapSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImplicitTreap, TreapMap, TreapSet};
+    use crate::data_structures::monoid::{MatrixProduct, Monoid, Sum};
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_implicit_treap() {
+        let mut treap: ImplicitTreap<Sum<i32>> = (1..=6).collect();
+        assert_eq!(treap.to_vec(), vec![1, 2, 3, 4, 5, 6]);
+
+        treap.reverse(1, 4);
+        assert_eq!(treap.to_vec(), vec![1, 5, 4, 3, 2, 6]);
+        assert_eq!(treap.query(0, 2), 10);
+
+        treap.insert_at(0, 10);
+        assert_eq!(treap.remove_at(3), Some(4));
+        assert_eq!(treap.to_vec(), vec![10, 1, 5, 3, 2, 6]);
+
+        // cut [1, 2] and paste it at the end
+        treap.move_range(1, 2, 4);
+        assert_eq!(treap.to_vec(), vec![10, 3, 2, 6, 1, 5]);
+
+        let tail = treap.split_off(4);
+        assert_eq!(treap.to_vec(), vec![10, 3, 2, 6]);
+        let head = treap.replace(tail);
+        assert_eq!(treap.to_vec(), vec![1, 5]);
+        treap.reverse(0, 1);
+        let tail = treap.replace(head);
+
+        treap.append(tail);
+        assert_eq!(treap.to_vec(), vec![10, 3, 2, 6, 5, 1]);
+        treap.reverse(4, 5);
+        treap.set(0, 0);
+        assert_eq!(treap.to_vec(), vec![0, 3, 2, 6, 1, 5]);
+        assert_eq!(treap.get(6), None);
+        assert_eq!(treap.remove_at(6), None);
+    }
+
+    #[test]
+    fn test_implicit_treap_sequences() {
+        let mut treap: ImplicitTreap<Sum<i32>> = (0..100).collect();
+        let num_nodes = treap.num_nodes();
+
+        // the nodes of a dropped sequence are reused
+        drop(treap.split_off(50));
+        for i in 0..50 {
+            treap.push_back(i);
+        }
+        assert_eq!(treap.num_nodes(), num_nodes);
+        assert_eq!(treap.query(0, 99), 2 * (0..50).sum::<i32>());
+
+        treap.reverse(0, 9);
+        let copy = treap.clone();
+        assert_eq!(copy.to_vec(), treap.to_vec());
+        assert_eq!(copy.get(0), Some(&9));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_implicit_treap_foreign_sequence() {
+        let mut a: ImplicitTreap<Sum<i32>> = (0..10).collect();
+        let mut b: ImplicitTreap<Sum<i32>> = (0..10).collect();
+        let tail = a.split_off(5);
+        b.append(tail);
+    }
+
+    #[test]
+    fn test_implicit_treap_random() {
+        // matrix products are not commutative, so reversals have to reverse the aggregate too
+        type M = MatrixProduct<i64, 2>;
+        let mut rng = thread_rng();
+        let gen = |rng: &mut ThreadRng| [[rng.gen_range(-1..=1), 1], [rng.gen_range(0..=1), 1]];
+        let mut naive: Vec<[[i64; 2]; 2]> = (0..50).map(|_| gen(&mut rng)).collect();
+        let mut treap: ImplicitTreap<M> = naive.iter().copied().collect();
+
+        for _ in 0..3000 {
+            let n = naive.len();
+            match rng.gen_range(0..7) {
+                0 => {
+                    let pos = rng.gen_range(0..=n);
+                    let val = gen(&mut rng);
+                    naive.insert(pos, val);
+                    treap.insert_at(pos, val);
+                }
+                1 if n > 0 => {
+                    let pos = rng.gen_range(0..n);
+                    assert_eq!(treap.remove_at(pos), Some(naive.remove(pos)));
+                }
+                2 if n > 0 => {
+                    let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                    let (l, r) = (a.min(b), a.max(b));
+                    naive[l..=r].reverse();
+                    treap.reverse(l, r);
+                }
+                3 if n > 0 => {
+                    let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                    let (l, r) = (a.min(b), a.max(b));
+                    let to = rng.gen_range(0..=n - (r - l + 1));
+                    let cut: Vec<_> = naive.drain(l..=r).collect();
+                    naive.splice(to..to, cut);
+                    treap.move_range(l, r, to);
+                }
+                4 if n > 0 => {
+                    let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                    let (l, r) = (a.min(b), a.max(b));
+                    let expected = naive[l..=r]
+                        .iter()
+                        .fold(M::identity(), |acc, x| M::combine(&acc, x));
+                    assert_eq!(treap.query(l, r), expected);
+                }
+                5 => {
+                    // rotate through a detached tail
+                    let pos = rng.gen_range(0..=n);
+                    naive.rotate_left(pos);
+                    let tail = treap.split_off(pos);
+                    let head = treap.replace(tail);
+                    treap.append(head);
+                }
+                _ if n > 0 => {
+                    let pos = rng.gen_range(0..n);
+                    assert_eq!(treap.get(pos), Some(&naive[pos]));
+                }
+                _ => {}
+            }
+
+            assert_eq!(treap.len(), naive.len());
+        }
+
+        assert_eq!(treap.to_vec(), naive);
+    }
+
+    #[test]
+    fn test_treap_map() {
+        let mut rng = thread_rng();
+        let mut map = TreapMap::new();
+        let mut naive = BTreeMap::new();
+
+        for _ in 0..5000 {
+            let key = rng.gen_range(0..300);
+            match rng.gen_range(0..3) {
+                0 => {
+                    let val = rng.gen::<u32>();
+                    assert_eq!(map.insert(key, val), naive.insert(key, val));
+                }
+                1 => assert_eq!(map.remove(&key), naive.remove(&key)),
+                _ => {
+                    assert_eq!(map.get(&key), naive.get(&key));
+                    assert_eq!(map.rank(&key), naive.range(..key).count());
+                    let k = rng.gen_range(0..=naive.len());
+                    assert_eq!(map.kth(k), naive.iter().nth(k));
+                }
+            }
+
+            assert_eq!(map.len(), naive.len());
+        }
+
+        assert!(map.iter().eq(naive.iter()));
+        if let Some(v) = map.get_mut(&naive.keys().next().copied().unwrap()) {
+            *v = 7;
+        }
+        assert_eq!(map.kth(0).map(|(_, v)| *v), Some(7));
+    }
+
+    #[test]
+    fn test_treap_set() {
+        let mut set = TreapSet::new();
+        for key in ["pear", "apple", "fig", "kiwi", "apple"] {
+            set.insert(key);
+        }
+
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.kth(0), Some(&"apple"));
+        assert_eq!(set.kth(3), Some(&"pear"));
+        assert_eq!(set.rank(&"grape"), 2);
+        assert!(set.remove(&"fig"));
+        assert!(!set.remove(&"fig"));
+        assert!(!set.contains(&"fig"));
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            vec!["apple", "kiwi", "pear"]
+        );
+    }
+}
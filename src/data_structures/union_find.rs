@@ -1,3 +1,9 @@
+use crate::data_structures::monoid::{Commutative, Invertible};
+use crate::data_structures::persistent_array::{PersistentArray, Version};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
 #[derive(Debug, Clone)]
 pub struct UnionFind {
     parent: Vec<usize>,
@@ -25,13 +31,32 @@ impl UnionFind {
         }
     }
 
+    /// Adds a new singleton set and returns its element.
+    pub fn make_set(&mut self) -> usize {
+        let i = self.parent.len();
+        self.parent.push(i);
+        self.rank.push(0);
+        self.set_size.push(1);
+        self.num_sets += 1;
+
+        i
+    }
+
+    /// Iterative, so arbitrarily long parent chains don't overflow the stack.
     pub fn find_set(&mut self, i: usize) -> usize {
-        if self.parent[i] == i {
-            return i;
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
         }
 
-        self.parent[i] = self.find_set(self.parent[i]);
-        self.parent[i]
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+
+        root
     }
 
     pub fn is_same_set(&mut self, i: usize, j: usize) -> bool {
@@ -72,6 +97,24 @@ impl UnionFind {
     pub fn set_size(&self) -> usize {
         self.set_size.len()
     }
+
+    /// Members of every set, sets ordered by their smallest element and members ascending.
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let n = self.parent.len();
+        let mut group_of = vec![usize::MAX; n];
+        let mut groups: Vec<Vec<usize>> = Vec::with_capacity(self.num_sets);
+
+        for i in 0..n {
+            let root = self.find_set(i);
+            if group_of[root] == usize::MAX {
+                group_of[root] = groups.len();
+                groups.push(Vec::with_capacity(self.set_size[root]));
+            }
+            groups[group_of[root]].push(i);
+        }
+
+        groups
+    }
 }
 
 impl Default for UnionFind {
@@ -80,9 +123,397 @@ impl Default for UnionFind {
     }
 }
 
+/// Union-Find over arbitrary hashable labels (strings, tuples, ...).
+/// Labels are mapped to indices of a `UnionFind` the first time they are seen.
+#[derive(Debug, Clone)]
+pub struct KeyedUnionFind<K: Hash + Eq + Clone> {
+    ids: HashMap<K, usize>,
+    keys: Vec<K>,
+    uf: UnionFind,
+}
+
+impl<K: Hash + Eq + Clone> KeyedUnionFind<K> {
+    pub fn new() -> Self {
+        KeyedUnionFind {
+            ids: HashMap::new(),
+            keys: Vec::new(),
+            uf: UnionFind::new(),
+        }
+    }
+
+    /// Index of the label, a new singleton set is created for unknown labels.
+    pub fn make_set(&mut self, key: K) -> usize {
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+
+        let id = self.uf.make_set();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        id
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    /// Representative label of the set containing `key`.
+    pub fn find_set(&mut self, key: &K) -> Option<&K> {
+        let id = *self.ids.get(key)?;
+        let root = self.uf.find_set(id);
+        Some(&self.keys[root])
+    }
+
+    pub fn is_same_set(&mut self, a: &K, b: &K) -> bool {
+        match (self.ids.get(a), self.ids.get(b)) {
+            (Some(&i), Some(&j)) => self.uf.is_same_set(i, j),
+            _ => false,
+        }
+    }
+
+    /// Merges the sets of both labels, creating them if needed.
+    pub fn union_set(&mut self, a: K, b: K) -> bool {
+        let i = self.make_set(a);
+        let j = self.make_set(b);
+        self.uf.union_set(i, j)
+    }
+
+    pub fn size_of_set(&mut self, key: &K) -> Option<usize> {
+        let id = *self.ids.get(key)?;
+        Some(self.uf.size_of_set(id))
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.uf.num_sets()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Labels of every set, in the order the labels were first seen.
+    pub fn groups(&mut self) -> Vec<Vec<K>> {
+        self.uf
+            .groups()
+            .into_iter()
+            .map(|group| group.into_iter().map(|i| self.keys[i].clone()).collect())
+            .collect()
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for KeyedUnionFind<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Union-Find that can undo merges.
+/// Uses union by size without path compression, so every merge changes exactly one parent
+/// pointer and `find_set` is O(log n). `rollback` undoes the merges made after a `snapshot`.
+#[derive(Debug, Clone, Default)]
+pub struct RollbackUnionFind {
+    parent: Vec<usize>,
+    set_size: Vec<usize>,
+    num_sets: usize,
+    // roots that were attached to another root, in order of the merges
+    history: Vec<usize>,
+}
+
+impl RollbackUnionFind {
+    pub fn with_capacity(n: usize) -> Self {
+        RollbackUnionFind {
+            parent: (0..n).collect(),
+            set_size: vec![1; n],
+            num_sets: n,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn find_set(&self, i: usize) -> usize {
+        let mut i = i;
+        while self.parent[i] != i {
+            i = self.parent[i];
+        }
+
+        i
+    }
+
+    pub fn is_same_set(&self, i: usize, j: usize) -> bool {
+        self.find_set(i) == self.find_set(j)
+    }
+
+    pub fn union_set(&mut self, i: usize, j: usize) -> bool {
+        let mut x = self.find_set(i);
+        let mut y = self.find_set(j);
+        if x == y {
+            return false;
+        }
+
+        if self.set_size[x] > self.set_size[y] {
+            std::mem::swap(&mut x, &mut y);
+        }
+        self.parent[x] = y;
+        self.set_size[y] += self.set_size[x];
+        self.num_sets -= 1;
+        self.history.push(x);
+
+        true
+    }
+
+    pub fn size_of_set(&self, i: usize) -> usize {
+        self.set_size[self.find_set(i)]
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    /// Current point in the history of merges, to be passed to `rollback`.
+    pub fn snapshot(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Undoes all merges made after the given snapshot.
+    pub fn rollback(&mut self, to: usize) {
+        while self.history.len() > to {
+            let x = self.history.pop().unwrap();
+            let y = self.parent[x];
+            self.set_size[y] -= self.set_size[x];
+            self.parent[x] = x;
+            self.num_sets += 1;
+        }
+    }
+}
+
+/// Error returned when a constraint contradicts the already known differences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction;
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "constraint contradicts the known differences")
+    }
+}
+
+impl std::error::Error for Contradiction {}
+
+/// Weighted (potential) Union-Find
+/// Keeps constraints of the form x_a - x_b = w over an abelian group (integers under
+/// addition, xor, residues, ...). Every element stores its difference to the parent,
+/// which is accumulated to a difference to the root while compressing paths.
+#[derive(Debug, Clone)]
+pub struct WeightedUnionFind<G: Commutative + Invertible> {
+    parent: Vec<usize>,
+    set_size: Vec<usize>,
+    // x_i - x_parent[i]
+    diff_parent: Vec<G::S>,
+    num_sets: usize,
+}
+
+impl<G> WeightedUnionFind<G>
+where
+    G: Commutative + Invertible,
+    G::S: PartialEq,
+{
+    pub fn with_capacity(n: usize) -> Self {
+        WeightedUnionFind {
+            parent: (0..n).collect(),
+            set_size: vec![1; n],
+            diff_parent: vec![G::identity(); n],
+            num_sets: n,
+        }
+    }
+
+    #[inline]
+    fn sub(a: &G::S, b: &G::S) -> G::S {
+        G::combine(a, &G::inverse(b))
+    }
+
+    pub fn find_set(&mut self, i: usize) -> usize {
+        let mut path = Vec::new();
+        let mut root = i;
+        while self.parent[root] != root {
+            path.push(root);
+            root = self.parent[root];
+        }
+
+        // nodes closer to the root are compressed first, so their parent difference
+        // is already relative to the root
+        for &x in path.iter().rev() {
+            let p = self.parent[x];
+            if p != root {
+                self.diff_parent[x] = G::combine(&self.diff_parent[x], &self.diff_parent[p]);
+                self.parent[x] = root;
+            }
+        }
+
+        root
+    }
+
+    // x_i - x_root
+    fn potential(&mut self, i: usize) -> G::S {
+        let root = self.find_set(i);
+        if root == i {
+            G::identity()
+        } else {
+            self.diff_parent[i].clone()
+        }
+    }
+
+    pub fn is_same_set(&mut self, i: usize, j: usize) -> bool {
+        self.find_set(i) == self.find_set(j)
+    }
+
+    /// Adds the constraint x_a - x_b = w.
+    /// Returns whether two sets were merged, or `Contradiction` if a and b are already
+    /// connected with a different difference.
+    pub fn union_with(&mut self, a: usize, b: usize, w: G::S) -> Result<bool, Contradiction> {
+        let (ra, rb) = (self.find_set(a), self.find_set(b));
+        let (pa, pb) = (self.potential(a), self.potential(b));
+
+        if ra == rb {
+            return if Self::sub(&pa, &pb) == w {
+                Ok(false)
+            } else {
+                Err(Contradiction)
+            };
+        }
+
+        // x_ra - x_rb = w - (x_a - x_ra) + (x_b - x_rb)
+        let d = G::combine(&Self::sub(&w, &pa), &pb);
+        if self.set_size[ra] <= self.set_size[rb] {
+            self.parent[ra] = rb;
+            self.diff_parent[ra] = d;
+            self.set_size[rb] += self.set_size[ra];
+        } else {
+            self.parent[rb] = ra;
+            self.diff_parent[rb] = G::inverse(&d);
+            self.set_size[ra] += self.set_size[rb];
+        }
+        self.num_sets -= 1;
+
+        Ok(true)
+    }
+
+    /// x_a - x_b if a and b are in the same set.
+    pub fn diff(&mut self, a: usize, b: usize) -> Option<G::S> {
+        if !self.is_same_set(a, b) {
+            return None;
+        }
+
+        let (pa, pb) = (self.potential(a), self.potential(b));
+        Some(Self::sub(&pa, &pb))
+    }
+
+    pub fn size_of_set(&mut self, i: usize) -> usize {
+        let x = self.find_set(i);
+        self.set_size[x]
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+}
+
+/// Handle of a version of a `PersistentUnionFind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnionFindVersion(usize);
+
+/// Persistent Union-Find
+/// Parents and set sizes are kept in persistent arrays, so `union_set` leaves the given
+/// version intact and returns a new one. Any version can be queried or extended, which
+/// allows branching version histories. Union by size without path compression keeps the
+/// trees O(log n) deep, so `find_set` is O(log^2 n).
+#[derive(Debug, Clone)]
+pub struct PersistentUnionFind {
+    parent: PersistentArray<usize>,
+    set_size: PersistentArray<usize>,
+    // (parent version, set size version, number of sets)
+    versions: Vec<(Version, Version, usize)>,
+}
+
+impl PersistentUnionFind {
+    pub fn with_capacity(n: usize) -> Self {
+        let parent = PersistentArray::new(&(0..n).collect::<Vec<_>>());
+        let set_size = PersistentArray::new(&vec![1; n]);
+        let versions = vec![(parent.base(), set_size.base(), n)];
+
+        PersistentUnionFind {
+            parent,
+            set_size,
+            versions,
+        }
+    }
+
+    /// Version with every element in its own set.
+    pub fn initial(&self) -> UnionFindVersion {
+        UnionFindVersion(0)
+    }
+
+    pub fn find_set(&self, version: UnionFindVersion, i: usize) -> usize {
+        let parent = self.versions[version.0].0;
+        let mut i = i;
+        loop {
+            let p = *self.parent.get(parent, i);
+            if p == i {
+                return i;
+            }
+            i = p;
+        }
+    }
+
+    pub fn is_same_set(&self, version: UnionFindVersion, i: usize, j: usize) -> bool {
+        self.find_set(version, i) == self.find_set(version, j)
+    }
+
+    /// Merges the sets of i and j on top of the given version.
+    /// Returns the given version itself if they are already in the same set.
+    pub fn union_set(&mut self, version: UnionFindVersion, i: usize, j: usize) -> UnionFindVersion {
+        let mut x = self.find_set(version, i);
+        let mut y = self.find_set(version, j);
+        if x == y {
+            return version;
+        }
+
+        let (parent, set_size, num_sets) = self.versions[version.0];
+        let (size_x, size_y) = (
+            *self.set_size.get(set_size, x),
+            *self.set_size.get(set_size, y),
+        );
+        if size_x > size_y {
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        let parent = self.parent.set(parent, x, y);
+        let set_size = self.set_size.set(set_size, y, size_x + size_y);
+        self.versions.push((parent, set_size, num_sets - 1));
+
+        UnionFindVersion(self.versions.len() - 1)
+    }
+
+    pub fn size_of_set(&self, version: UnionFindVersion, i: usize) -> usize {
+        let root = self.find_set(version, i);
+        *self.set_size.get(self.versions[version.0].1, root)
+    }
+
+    pub fn num_sets(&self, version: UnionFindVersion) -> usize {
+        self.versions[version.0].2
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::UnionFind;
+    use super::{
+        Contradiction, KeyedUnionFind, PersistentUnionFind, RollbackUnionFind, UnionFind,
+        WeightedUnionFind,
+    };
+    use crate::data_structures::monoid::{Sum, Xor};
+    use crate::data_structures::test_utils::{ModInt, MOD};
+    use rand::prelude::*;
 
     #[test]
     fn test_union_find() {
@@ -127,4 +558,197 @@ mod tests {
         assert_eq!(uf.size_of_set(3), 5);
         assert_eq!(uf.size_of_set(4), 5);
     }
+
+    #[test]
+    fn test_union_find_make_set_and_groups() {
+        let mut uf = UnionFind::new();
+        for i in 0..6 {
+            assert_eq!(uf.make_set(), i);
+        }
+        assert_eq!(uf.num_sets(), 6);
+
+        uf.union_set(4, 1);
+        uf.union_set(5, 0);
+        uf.union_set(1, 3);
+        assert_eq!(uf.groups(), vec![vec![0, 5], vec![1, 3, 4], vec![2]]);
+
+        let i = uf.make_set();
+        uf.union_set(i, 2);
+        assert_eq!(uf.num_sets(), 3);
+        assert_eq!(uf.size_of_set(2), 2);
+        assert_eq!(uf.groups().len(), 3);
+    }
+
+    #[test]
+    fn test_union_find_long_chain() {
+        let n = 1_000_000;
+        let mut uf = UnionFind::with_capacity(n);
+        for i in 0..n - 1 {
+            uf.union_set(i, i + 1);
+        }
+
+        let root = uf.find_set(n - 1);
+        assert_eq!(uf.find_set(0), root);
+        assert_eq!(uf.find_set(n / 2), root);
+        assert_eq!(uf.size_of_set(0), n);
+        assert_eq!(uf.num_sets(), 1);
+    }
+
+    #[test]
+    fn test_keyed_union_find() {
+        let mut uf = KeyedUnionFind::new();
+        assert!(uf.union_set("alice", "bob"));
+        assert!(uf.union_set("carol", "dave"));
+        assert!(!uf.union_set("bob", "alice"));
+        uf.make_set("eve");
+
+        assert!(uf.is_same_set(&"alice", &"bob"));
+        assert!(!uf.is_same_set(&"alice", &"carol"));
+        assert!(!uf.is_same_set(&"alice", &"mallory"));
+        assert_eq!(uf.num_sets(), 3);
+        assert_eq!(uf.len(), 5);
+
+        uf.union_set("dave", "bob");
+        assert_eq!(uf.size_of_set(&"carol"), Some(4));
+        assert_eq!(uf.size_of_set(&"mallory"), None);
+        let root = uf.find_set(&"dave").copied();
+        assert_eq!(uf.find_set(&"alice").copied(), root);
+        assert_eq!(
+            uf.groups(),
+            vec![vec!["alice", "bob", "carol", "dave"], vec!["eve"]]
+        );
+
+        let mut grid = KeyedUnionFind::new();
+        grid.union_set((0, 0), (0, 1));
+        grid.union_set((0, 1), (1, 1));
+        assert!(grid.is_same_set(&(0, 0), &(1, 1)));
+        assert!(grid.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn test_rollback_union_find() {
+        let mut uf = RollbackUnionFind::with_capacity(6);
+        uf.union_set(0, 1);
+        uf.union_set(2, 3);
+        let snapshot = uf.snapshot();
+
+        assert!(uf.union_set(1, 3));
+        assert!(!uf.union_set(0, 2));
+        uf.union_set(4, 5);
+        assert!(uf.is_same_set(0, 2));
+        assert_eq!(uf.size_of_set(3), 4);
+        assert_eq!(uf.num_sets(), 2);
+
+        uf.rollback(snapshot);
+        assert!(!uf.is_same_set(0, 2));
+        assert!(!uf.is_same_set(4, 5));
+        assert!(uf.is_same_set(0, 1));
+        assert_eq!(uf.size_of_set(3), 2);
+        assert_eq!(uf.num_sets(), 4);
+
+        uf.rollback(0);
+        assert_eq!(uf.num_sets(), 6);
+        assert_eq!(uf.size_of_set(0), 1);
+    }
+
+    #[test]
+    fn test_weighted_union_find() {
+        let mut uf = WeightedUnionFind::<Sum<i64>>::with_capacity(5);
+
+        assert_eq!(uf.union_with(0, 1, 3), Ok(true));
+        assert_eq!(uf.union_with(2, 1, -2), Ok(true));
+        assert_eq!(uf.diff(0, 2), Some(5));
+        assert_eq!(uf.diff(2, 0), Some(-5));
+        assert_eq!(uf.diff(0, 3), None);
+
+        assert_eq!(uf.union_with(0, 2, 5), Ok(false));
+        assert_eq!(uf.union_with(0, 2, 4), Err(Contradiction));
+
+        assert_eq!(uf.union_with(3, 4, 10), Ok(true));
+        assert_eq!(uf.union_with(4, 2, 1), Ok(true));
+        assert_eq!(uf.diff(3, 0), Some(10 + 1 - 5));
+        assert_eq!(uf.num_sets(), 1);
+        assert_eq!(uf.size_of_set(4), 5);
+    }
+
+    #[test]
+    fn test_weighted_union_find_random() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let values: Vec<u32> = (0..n).map(|_| rng.gen()).collect();
+        let mut uf = WeightedUnionFind::<Xor<u32>>::with_capacity(n);
+        let mut plain = UnionFind::with_capacity(n);
+
+        for _ in 0..1000 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            if rng.gen_bool(0.5) {
+                let merged = uf.union_with(a, b, values[a] ^ values[b]);
+                assert_eq!(merged, Ok(plain.union_set(a, b)));
+            } else if plain.is_same_set(a, b) {
+                assert_eq!(uf.diff(a, b), Some(values[a] ^ values[b]));
+                assert!(uf.union_with(a, b, values[a] ^ values[b] ^ 1).is_err());
+            } else {
+                assert_eq!(uf.diff(a, b), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weighted_union_find_mod_int() {
+        let mut uf = WeightedUnionFind::<Sum<ModInt>>::with_capacity(3);
+        uf.union_with(0, 1, ModInt(MOD - 1)).unwrap();
+        uf.union_with(1, 2, ModInt(5)).unwrap();
+        assert_eq!(uf.diff(0, 2), Some(ModInt(4)));
+        assert_eq!(uf.diff(2, 0), Some(ModInt(MOD - 4)));
+    }
+
+    #[test]
+    fn test_persistent_union_find() {
+        let mut uf = PersistentUnionFind::with_capacity(5);
+        let v0 = uf.initial();
+        let v1 = uf.union_set(v0, 0, 1);
+        let v2 = uf.union_set(v1, 1, 2);
+        // branch off v1
+        let v3 = uf.union_set(v1, 3, 4);
+
+        assert!(!uf.is_same_set(v0, 0, 1));
+        assert!(uf.is_same_set(v1, 0, 1));
+        assert!(uf.is_same_set(v2, 0, 2));
+        assert!(!uf.is_same_set(v3, 0, 2));
+        assert!(uf.is_same_set(v3, 3, 4));
+        assert!(!uf.is_same_set(v2, 3, 4));
+
+        assert_eq!(uf.size_of_set(v2, 1), 3);
+        assert_eq!(uf.size_of_set(v3, 1), 2);
+        assert_eq!(uf.num_sets(v0), 5);
+        assert_eq!(uf.num_sets(v2), 3);
+        assert_eq!(uf.num_sets(v3), 3);
+        assert_eq!(uf.union_set(v2, 0, 2), v2);
+
+        let uf = PersistentUnionFind::with_capacity(0);
+        assert_eq!(uf.num_sets(uf.initial()), 0);
+    }
+
+    #[test]
+    fn test_persistent_union_find_random() {
+        let n = 30;
+        let mut rng = thread_rng();
+        let mut uf = PersistentUnionFind::with_capacity(n);
+        let mut versions = vec![(uf.initial(), UnionFind::with_capacity(n))];
+
+        for _ in 0..500 {
+            let (version, mut plain) = versions.choose(&mut rng).unwrap().clone();
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+
+            if rng.gen_bool(0.5) {
+                let next = uf.union_set(version, a, b);
+                plain.union_set(a, b);
+                versions.push((next, plain));
+            } else {
+                assert_eq!(uf.is_same_set(version, a, b), plain.is_same_set(a, b));
+                assert_eq!(uf.size_of_set(version, a), plain.size_of_set(a));
+                assert_eq!(uf.num_sets(version), plain.num_sets());
+            }
+        }
+    }
 }
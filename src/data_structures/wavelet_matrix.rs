@@ -0,0 +1,332 @@
+/// Succinct Bit Vector
+/// Bits packed into words together with the number of ones before every word,
+/// which answers rank queries in O(1) with one extra counter per 64 bits.
+#[derive(Debug, Clone)]
+pub struct BitVector {
+    n: usize,
+    words: Vec<u64>,
+    // ones in words[..i]
+    ranks: Vec<usize>,
+}
+
+impl BitVector {
+    pub fn new(bits: &[bool]) -> Self {
+        let n = bits.len();
+        let mut words = vec![0u64; n / 64 + 1];
+        for (i, &bit) in bits.iter().enumerate() {
+            if bit {
+                words[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        let mut ranks = vec![0; words.len() + 1];
+        for (i, word) in words.iter().enumerate() {
+            ranks[i + 1] = ranks[i] + word.count_ones() as usize;
+        }
+
+        BitVector { n, words, ranks }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.n, "index out of range");
+        (self.words[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Number of ones in the first `i` bits.
+    pub fn rank1(&self, i: usize) -> usize {
+        let mask = (1u64 << (i % 64)) - 1;
+        self.ranks[i / 64] + (self.words[i / 64] & mask).count_ones() as usize
+    }
+
+    /// Number of zeros in the first `i` bits.
+    pub fn rank0(&self, i: usize) -> usize {
+        i - self.rank1(i)
+    }
+}
+
+/// Wavelet Matrix
+/// Static array of compressed values stored bit by bit from the most significant bit.
+/// On every level the elements are stably partitioned by the current bit (zeros first),
+/// so a range of the array maps to a range of the next level through rank queries.
+/// Every query walks the levels once, O(log σ) where σ is the number of distinct values.
+#[derive(Debug, Clone)]
+pub struct WaveletMatrix<T: Ord + Clone> {
+    n: usize,
+    values: Vec<T>,
+    levels: Vec<BitVector>,
+    // number of zeros on every level
+    mids: Vec<usize>,
+}
+
+impl<T: Ord + Clone> WaveletMatrix<T> {
+    pub fn new(nums: &[T]) -> Self {
+        let mut values = nums.to_vec();
+        values.sort();
+        values.dedup();
+
+        let bits = (usize::BITS - values.len().saturating_sub(1).leading_zeros()) as usize;
+        let mut cur: Vec<usize> = nums
+            .iter()
+            .map(|num| values.binary_search(num).unwrap())
+            .collect();
+
+        let mut levels = Vec::with_capacity(bits);
+        let mut mids = Vec::with_capacity(bits);
+        for level in (0..bits).rev() {
+            let row: Vec<bool> = cur.iter().map(|&v| (v >> level) & 1 == 1).collect();
+            let (zeros, ones): (Vec<usize>, Vec<usize>) =
+                cur.iter().partition(|&&v| (v >> level) & 1 == 0);
+
+            mids.push(zeros.len());
+            levels.push(BitVector::new(&row));
+            cur = zeros;
+            cur.extend(ones);
+        }
+
+        WaveletMatrix {
+            n: nums.len(),
+            values,
+            levels,
+            mids,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    fn bits(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn get(&self, i: usize) -> &T {
+        let mut i = i;
+        let mut res = 0;
+        for (d, bv) in self.levels.iter().enumerate() {
+            if bv.get(i) {
+                res |= 1 << (self.bits() - 1 - d);
+                i = self.mids[d] + bv.rank1(i);
+            } else {
+                i = bv.rank0(i);
+            }
+        }
+
+        &self.values[res]
+    }
+
+    /// k-th (0-based) smallest value in the inclusive range [l, r].
+    pub fn kth_smallest(&self, l: usize, r: usize, k: usize) -> Option<&T> {
+        if l > r || k > r - l {
+            return None;
+        }
+
+        let (mut l, mut r) = (l, r + 1);
+        let mut k = k;
+        let mut res = 0;
+        for (d, bv) in self.levels.iter().enumerate() {
+            let (l0, r0) = (bv.rank0(l), bv.rank0(r));
+            if k < r0 - l0 {
+                l = l0;
+                r = r0;
+            } else {
+                k -= r0 - l0;
+                res |= 1 << (self.bits() - 1 - d);
+                l = self.mids[d] + l - l0;
+                r = self.mids[d] + r - r0;
+            }
+        }
+
+        Some(&self.values[res])
+    }
+
+    /// k-th (0-based) largest value in the inclusive range [l, r].
+    pub fn kth_largest(&self, l: usize, r: usize, k: usize) -> Option<&T> {
+        if l > r || k > r - l {
+            return None;
+        }
+
+        self.kth_smallest(l, r, r - l - k)
+    }
+
+    /// Number of occurrences of `x` among the first `i` elements.
+    pub fn rank(&self, x: &T, i: usize) -> usize {
+        let Ok(x) = self.values.binary_search(x) else {
+            return 0;
+        };
+
+        let (mut l, mut r) = (0, i);
+        for (d, bv) in self.levels.iter().enumerate() {
+            if (x >> (self.bits() - 1 - d)) & 1 == 1 {
+                l = self.mids[d] + bv.rank1(l);
+                r = self.mids[d] + bv.rank1(r);
+            } else {
+                l = bv.rank0(l);
+                r = bv.rank0(r);
+            }
+        }
+
+        r - l
+    }
+
+    // number of compressed values < x in the half-open range [l, r)
+    fn count_less(&self, l: usize, r: usize, x: usize) -> usize {
+        if x >= self.values.len() {
+            return r - l;
+        }
+
+        let (mut l, mut r) = (l, r);
+        let mut res = 0;
+        for (d, bv) in self.levels.iter().enumerate() {
+            let (l0, r0) = (bv.rank0(l), bv.rank0(r));
+            if (x >> (self.bits() - 1 - d)) & 1 == 1 {
+                res += r0 - l0;
+                l = self.mids[d] + l - l0;
+                r = self.mids[d] + r - r0;
+            } else {
+                l = l0;
+                r = r0;
+            }
+        }
+
+        res
+    }
+
+    /// Number of values in [lo, hi] within the inclusive range [l, r].
+    pub fn range_freq(&self, l: usize, r: usize, lo: &T, hi: &T) -> usize {
+        if l > r || lo > hi {
+            return 0;
+        }
+
+        let lo = self.values.partition_point(|v| v < lo);
+        let hi = self.values.partition_point(|v| v <= hi);
+        self.count_less(l, r + 1, hi) - self.count_less(l, r + 1, lo)
+    }
+
+    /// Largest value `<= x` in the inclusive range [l, r].
+    pub fn prev_value(&self, l: usize, r: usize, x: &T) -> Option<&T> {
+        if l > r {
+            return None;
+        }
+
+        let cnt = self.count_less(l, r + 1, self.values.partition_point(|v| v <= x));
+        if cnt == 0 {
+            return None;
+        }
+
+        self.kth_smallest(l, r, cnt - 1)
+    }
+
+    /// Smallest value `>= x` in the inclusive range [l, r].
+    pub fn next_value(&self, l: usize, r: usize, x: &T) -> Option<&T> {
+        if l > r {
+            return None;
+        }
+
+        let cnt = self.count_less(l, r + 1, self.values.partition_point(|v| v < x));
+        self.kth_smallest(l, r, cnt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitVector, WaveletMatrix};
+    use rand::prelude::*;
+    use std::cmp::{max, min};
+
+    #[test]
+    fn test_bit_vector() {
+        let mut rng = thread_rng();
+        let bits: Vec<bool> = (0..300).map(|_| rng.gen_bool(0.3)).collect();
+        let bv = BitVector::new(&bits);
+
+        let mut ones = 0;
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(bv.rank1(i), ones);
+            assert_eq!(bv.rank0(i), i - ones);
+            assert_eq!(bv.get(i), bit);
+            ones += bit as usize;
+        }
+        assert_eq!(bv.rank1(bits.len()), ones);
+    }
+
+    #[test]
+    fn test_wavelet_matrix() {
+        let nums = [5, 4, 5, 5, 2, 1, 5, 6, 1, 3, 8, 0];
+        let wm = WaveletMatrix::new(&nums);
+
+        assert_eq!(wm.len(), nums.len());
+        assert_eq!(wm.kth_smallest(0, 11, 0), Some(&0));
+        assert_eq!(wm.kth_smallest(2, 7, 2), Some(&5));
+        assert_eq!(wm.kth_largest(0, 11, 0), Some(&8));
+        assert_eq!(wm.kth_smallest(2, 7, 6), None);
+        assert_eq!(wm.rank(&5, 12), 4);
+        assert_eq!(wm.rank(&5, 3), 2);
+        assert_eq!(wm.rank(&7, 12), 0);
+        assert_eq!(wm.range_freq(0, 11, &2, &5), 7);
+        assert_eq!(wm.prev_value(4, 9, &4), Some(&3));
+        assert_eq!(wm.prev_value(4, 8, &0), None);
+        assert_eq!(wm.next_value(4, 9, &4), Some(&5));
+        assert_eq!(wm.next_value(4, 9, &7), None);
+        assert_eq!(*wm.get(7), 6);
+    }
+
+    #[test]
+    fn test_wavelet_matrix_random() {
+        let n = 200;
+        let mut rng = thread_rng();
+        let nums: Vec<i64> = (0..n).map(|_| rng.gen_range(-50..50)).collect();
+        let wm = WaveletMatrix::new(&nums);
+
+        for (i, num) in nums.iter().enumerate() {
+            assert_eq!(wm.get(i), num);
+        }
+
+        for _ in 0..1000 {
+            let v1 = rng.gen_range(0..n);
+            let v2 = rng.gen_range(0..n);
+            let (l, r) = (min(v1, v2), max(v1, v2));
+            let mut sorted = nums[l..=r].to_vec();
+            sorted.sort();
+
+            let k = rng.gen_range(0..sorted.len());
+            assert_eq!(wm.kth_smallest(l, r, k), Some(&sorted[k]));
+            assert_eq!(wm.kth_largest(l, r, k), Some(&sorted[sorted.len() - 1 - k]));
+
+            let x = rng.gen_range(-60..60);
+            let y = rng.gen_range(-60..60);
+            let expected = sorted.iter().filter(|&&v| x <= v && v <= y).count();
+            assert_eq!(wm.range_freq(l, r, &x, &y), expected);
+            assert_eq!(
+                wm.rank(&x, r + 1) - wm.rank(&x, l),
+                sorted.iter().filter(|&&v| v == x).count()
+            );
+            assert_eq!(
+                wm.prev_value(l, r, &x),
+                sorted.iter().rev().find(|&&v| v <= x)
+            );
+            assert_eq!(wm.next_value(l, r, &x), sorted.iter().find(|&&v| v >= x));
+        }
+    }
+
+    #[test]
+    fn test_wavelet_matrix_single_value() {
+        let wm = WaveletMatrix::new(&[7, 7, 7]);
+        assert_eq!(wm.len(), 3);
+        assert_eq!(wm.kth_smallest(0, 2, 2), Some(&7));
+        assert_eq!(wm.range_freq(1, 2, &0, &7), 2);
+        assert_eq!(wm.prev_value(0, 2, &6), None);
+        assert_eq!(wm.rank(&7, 2), 2);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::data_structures::union_find::RollbackUnionFind;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Offline dynamic connectivity
+/// Answers "are u and v connected" queries interleaved with edge insertions and deletions
+/// in O((n + q) log q log n) overall.
+/// Every edge is alive during an interval of queries. The intervals are inserted into a
+/// segment tree over the query indices and a DFS over that tree merges the edges of a node
+/// into a rollback union-find when entering it and undoes them when leaving it, so at each
+/// leaf exactly the edges alive at that query are merged.
+#[derive(Debug, Clone, Default)]
+pub struct DynamicConnectivity {
+    n: usize,
+    // query indices at which the currently present copies of an edge were inserted
+    alive: HashMap<(usize, usize), Vec<usize>>,
+    // (from query index, to query index exclusive, u, v)
+    intervals: Vec<(usize, usize, usize, usize)>,
+    queries: Vec<(usize, usize)>,
+}
+
+impl DynamicConnectivity {
+    pub fn new(n: usize) -> Self {
+        DynamicConnectivity {
+            n,
+            alive: HashMap::new(),
+            intervals: Vec::new(),
+            queries: Vec::new(),
+        }
+    }
+
+    fn key(u: usize, v: usize) -> (usize, usize) {
+        (u.min(v), u.max(v))
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize) -> Result<()> {
+        if u >= self.n || v >= self.n {
+            bail!("Invalid node index");
+        }
+
+        self.alive
+            .entry(Self::key(u, v))
+            .or_default()
+            .push(self.queries.len());
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, u: usize, v: usize) -> Result<()> {
+        let key = Self::key(u, v);
+        let Some(start) = self.alive.get_mut(&key).and_then(|starts| starts.pop()) else {
+            bail!("Edge {}-{} is not present", u, v);
+        };
+
+        self.intervals
+            .push((start, self.queries.len(), key.0, key.1));
+        Ok(())
+    }
+
+    /// Asks whether u and v are connected at this point, the answer is at the same
+    /// index of the result of `solve`.
+    pub fn query(&mut self, u: usize, v: usize) -> Result<usize> {
+        if u >= self.n || v >= self.n {
+            bail!("Invalid node index");
+        }
+
+        self.queries.push((u, v));
+        Ok(self.queries.len() - 1)
+    }
+
+    pub fn solve(&self) -> Vec<bool> {
+        let q = self.queries.len();
+        if q == 0 {
+            return Vec::new();
+        }
+
+        let mut tree: Vec<Vec<(usize, usize)>> = vec![Vec::new(); 4 * q];
+        let still_alive = self
+            .alive
+            .iter()
+            .flat_map(|(&(u, v), starts)| starts.iter().map(move |&start| (start, q, u, v)));
+        for (from, to, u, v) in self.intervals.iter().copied().chain(still_alive) {
+            if from < to {
+                Self::insert(&mut tree, 1, 0, q - 1, from, to - 1, (u, v));
+            }
+        }
+
+        let mut uf = RollbackUnionFind::with_capacity(self.n);
+        let mut res = vec![false; q];
+        self.dfs(&tree, &mut uf, 1, 0, q - 1, &mut res);
+
+        res
+    }
+
+    fn insert(
+        tree: &mut [Vec<(usize, usize)>],
+        p: usize,
+        left: usize,
+        right: usize,
+        i: usize,
+        j: usize,
+        edge: (usize, usize),
+    ) {
+        if j < left || right < i {
+            return;
+        }
+
+        if i <= left && right <= j {
+            tree[p].push(edge);
+            return;
+        }
+
+        let m = (right - left) / 2 + left;
+        Self::insert(tree, p << 1, left, m, i, j, edge);
+        Self::insert(tree, (p << 1) + 1, m + 1, right, i, j, edge);
+    }
+
+    fn dfs(
+        &self,
+        tree: &[Vec<(usize, usize)>],
+        uf: &mut RollbackUnionFind,
+        p: usize,
+        left: usize,
+        right: usize,
+        res: &mut [bool],
+    ) {
+        let snapshot = uf.snapshot();
+        for &(u, v) in tree[p].iter() {
+            uf.union_set(u, v);
+        }
+
+        if left == right {
+            let (u, v) = self.queries[left];
+            res[left] = uf.is_same_set(u, v);
+        } else {
+            let m = (right - left) / 2 + left;
+            self.dfs(tree, uf, p << 1, left, m, res);
+            self.dfs(tree, uf, (p << 1) + 1, m + 1, right, res);
+        }
+
+        uf.rollback(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynamicConnectivity;
+    use crate::data_structures::union_find::UnionFind;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_dynamic_connectivity() {
+        let mut dc = DynamicConnectivity::new(4);
+        dc.add_edge(0, 1).unwrap();
+        dc.add_edge(1, 2).unwrap();
+        dc.query(0, 2).unwrap();
+        dc.remove_edge(2, 1).unwrap();
+        dc.query(0, 2).unwrap();
+        dc.query(0, 1).unwrap();
+        dc.add_edge(2, 3).unwrap();
+        dc.add_edge(3, 0).unwrap();
+        dc.query(1, 2).unwrap();
+
+        assert_eq!(dc.solve(), vec![true, false, true, true]);
+        assert!(dc.remove_edge(1, 2).is_err());
+        assert!(dc.add_edge(0, 4).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_connectivity_random() {
+        let n = 12;
+        let mut rng = thread_rng();
+        let mut dc = DynamicConnectivity::new(n);
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut expected = Vec::new();
+
+        for _ in 0..2000 {
+            match rng.gen_range(0..3) {
+                0 => {
+                    let (u, v) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                    dc.add_edge(u, v).unwrap();
+                    edges.push((u, v));
+                }
+                1 if !edges.is_empty() => {
+                    let (u, v) = edges.swap_remove(rng.gen_range(0..edges.len()));
+                    dc.remove_edge(u, v).unwrap();
+                }
+                _ => {
+                    let (u, v) = (rng.gen_range(0..n), rng.gen_range(0..n));
+                    dc.query(u, v).unwrap();
+
+                    let mut uf = UnionFind::with_capacity(n);
+                    for &(a, b) in edges.iter() {
+                        uf.union_set(a, b);
+                    }
+                    expected.push(uf.is_same_set(u, v));
+                }
+            }
+        }
+
+        assert_eq!(dc.solve(), expected);
+    }
+}
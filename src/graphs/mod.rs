@@ -1,6 +1,7 @@
 pub mod articulation_point;
 pub mod bellman_ford;
 pub mod cycle_check;
+pub mod dynamic_connectivity;
 pub mod graph_matching;
 pub mod hungarian_max_matching;
 pub mod maximum_flow;